@@ -0,0 +1,181 @@
+use std::f64::consts::{E, PI};
+
+use thiserror::Error;
+
+use super::tokens::number;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
+pub enum CalcError {
+    #[error("Expression ended early")]
+    UnexpectedEnd,
+    #[error("Unexpected token in expression: '{0}'")]
+    UnexpectedToken(String),
+    #[error("Unknown name in expression: '{0}'")]
+    UnknownName(String),
+}
+
+/// Evaluates an Octo expression, such as the body of a `:calc`
+///
+/// Like Octo, operators have no precedence and are evaluated from right to left, so
+/// `2 * 3 + 1` is 8, with parentheses to group them otherwise. Names other than `PI` and `E`
+/// are looked up with `lookup`.
+/// ```
+/// # use eoxchip8::assembler::calc::evaluate;
+/// let value = |name: &str| (name == "WIDTH").then_some(64.0);
+/// assert_eq!(evaluate(&["2", "*", "3", "+", "1"], value), Ok(8.0));
+/// assert_eq!(evaluate(&["(", "2", "*", "3", ")", "+", "1"], value), Ok(7.0));
+/// assert_eq!(evaluate(&["WIDTH", "/", "2"], value), Ok(32.0));
+/// ```
+pub fn evaluate(tokens: &[&str], lookup: impl Fn(&str) -> Option<f64>) -> Result<f64, CalcError> {
+    let mut evaluator = Evaluator {
+        tokens,
+        position: 0,
+        lookup,
+    };
+    let value = evaluator.expression()?;
+    match evaluator.next() {
+        Ok(token) => Err(CalcError::UnexpectedToken(token.to_owned())),
+        Err(_) => Ok(value),
+    }
+}
+
+struct Evaluator<'a, F> {
+    tokens: &'a [&'a str],
+    position: usize,
+    lookup: F,
+}
+
+impl<'a, F: Fn(&str) -> Option<f64>> Evaluator<'a, F> {
+    fn next(&mut self) -> Result<&'a str, CalcError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or(CalcError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expression(&mut self) -> Result<f64, CalcError> {
+        let left = self.term()?;
+        match self.tokens.get(self.position) {
+            None | Some(&")") => Ok(left),
+            Some(_) => {
+                let operator = self.next()?;
+                let right = self.expression()?;
+                binary(operator, left, right)
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, CalcError> {
+        let token = self.next()?;
+        if token == "(" {
+            let value = self.expression()?;
+            return match self.next()? {
+                ")" => Ok(value),
+                token => Err(CalcError::UnexpectedToken(token.to_owned())),
+            };
+        }
+        if let Some(value) = number(token) {
+            #[allow(clippy::cast_precision_loss)]
+            return Ok(value as f64);
+        }
+        if is_unary(token) {
+            let value = self.term()?;
+            return Ok(unary(token, value));
+        }
+        match token {
+            "PI" => Ok(PI),
+            "E" => Ok(E),
+            name => (self.lookup)(name).ok_or_else(|| CalcError::UnknownName(name.to_owned())),
+        }
+    }
+}
+
+fn is_unary(operator: &str) -> bool {
+    matches!(
+        operator,
+        "-" | "~"
+            | "!"
+            | "abs"
+            | "sqrt"
+            | "sin"
+            | "cos"
+            | "tan"
+            | "exp"
+            | "log"
+            | "sign"
+            | "ceil"
+            | "floor"
+    )
+}
+
+fn unary(operator: &str, value: f64) -> f64 {
+    match operator {
+        "-" => -value,
+        "~" => from_integer(!to_integer(value)),
+        "!" => from_bool(value == 0.0),
+        "abs" => value.abs(),
+        "sqrt" => value.sqrt(),
+        "sin" => value.sin(),
+        "cos" => value.cos(),
+        "tan" => value.tan(),
+        "exp" => value.exp(),
+        "log" => value.ln(),
+        "sign" => {
+            if value == 0.0 {
+                0.0
+            } else {
+                value.signum()
+            }
+        }
+        "ceil" => value.ceil(),
+        _ => value.floor(),
+    }
+}
+
+/// Comparisons are exact, and shifts wrap their amount around the width of an `i64`
+#[allow(
+    clippy::float_cmp,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn binary(operator: &str, left: f64, right: f64) -> Result<f64, CalcError> {
+    let (left_integer, right_integer) = (to_integer(left), to_integer(right));
+    Ok(match operator {
+        "+" => left + right,
+        "-" => left - right,
+        "*" => left * right,
+        "/" => left / right,
+        "%" => left % right,
+        "pow" => left.powf(right),
+        "min" => left.min(right),
+        "max" => left.max(right),
+        "&" => from_integer(left_integer & right_integer),
+        "|" => from_integer(left_integer | right_integer),
+        "^" => from_integer(left_integer ^ right_integer),
+        "<<" => from_integer(left_integer.wrapping_shl(right_integer as u32)),
+        ">>" => from_integer(left_integer.wrapping_shr(right_integer as u32)),
+        "<" => from_bool(left < right),
+        ">" => from_bool(left > right),
+        "<=" => from_bool(left <= right),
+        ">=" => from_bool(left >= right),
+        "==" => from_bool(left == right),
+        "!=" => from_bool(left != right),
+        operator => return Err(CalcError::UnexpectedToken(operator.to_owned())),
+    })
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn to_integer(value: f64) -> i64 {
+    value as i64
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn from_integer(value: i64) -> f64 {
+    value as f64
+}
+
+fn from_bool(value: bool) -> f64 {
+    f64::from(u8::from(value))
+}
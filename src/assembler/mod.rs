@@ -0,0 +1,3 @@
+pub mod calc;
+pub mod octo;
+pub mod tokens;
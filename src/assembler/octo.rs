@@ -0,0 +1,1100 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use thiserror::Error;
+
+use crate::core::{
+    cpu::instructions::{Instruction, InstructionEncodeError, LONG_LOAD_I_PREFIX},
+    memory::{Address, PROGRAM_START, XO_CHIP_MEMORY_SIZE},
+};
+
+use super::{
+    calc::{self, CalcError},
+    tokens::{self, Token},
+};
+
+/// The label programs start at, if they define it
+pub const MAIN_LABEL: &str = "main";
+
+/// The most tokens macros may expand to, so a macro calling itself can't expand forever
+const MAX_MACRO_TOKENS: usize = 1 << 18;
+
+/// A program assembled from Octo source
+#[derive(Default, Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct Assembled {
+    /// The program, to be loaded at 0x200
+    pub rom: Vec<u8>,
+    /// The address of every label
+    pub symbols: BTreeMap<String, Address>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
+#[error("Error on line {line}: {kind}")]
+pub struct AssembleError {
+    pub line: usize,
+    pub kind: AssembleErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
+pub enum AssembleErrorKind {
+    #[error("Source ended early")]
+    UnexpectedEnd,
+    #[error("Unexpected token: '{0}'")]
+    UnexpectedToken(String),
+    #[error("Expected a register: '{0}'")]
+    ExpectedRegister(String),
+    #[error("Expected a number: '{0}'")]
+    ExpectedNumber(String),
+    #[error("Value out of range: {0}")]
+    ValueOutOfRange(i64),
+    #[error("Name defined twice: '{0}'")]
+    Redefined(String),
+    #[error("Undefined label: '{0}'")]
+    UndefinedLabel(String),
+    #[error("'{0}' outside of a matching block")]
+    UnmatchedBlock(String),
+    #[error("Block never closed")]
+    UnclosedBlock,
+    #[error("Macro '{0}' expands without end")]
+    MacroRecursion(String),
+    #[error("Program doesn't fit in memory")]
+    ProgramTooLarge,
+    #[error("Error on encoding instruction: '{0}'")]
+    Encode(#[from] InstructionEncodeError),
+    #[error("Error on evaluating expression: '{0}'")]
+    Calc(#[from] CalcError),
+}
+
+/// Assembles a program written for the Octo assembler
+///
+/// Supports labels, `:const`, `:alias`, `:macro`, `:calc`, `:org`, `:byte`, `:pointer` and
+/// `:call`, bare numbers as data, the `if ... then`, `if ... begin ... else ... end` and
+/// `loop ... while ... again` control flow, and every CHIP-8, SUPER-CHIP and XO-CHIP
+/// statement. As in Octo, programs defining `main` start with a jump to it, unless it's
+/// already first.
+/// ```
+/// # use eoxchip8::{assembler::octo::assemble, core::memory::Address};
+/// let assembled = assemble(": main\n  v0 := 1\n  loop again").unwrap();
+/// assert_eq!(assembled.rom, [0x60, 0x01, 0x12, 0x02]);
+/// assert_eq!(assembled.symbols["main"], Address(0x200));
+/// ```
+pub fn assemble(source: &str) -> Result<Assembled, AssembleError> {
+    let tokens = tokens::tokenize(source);
+    if !may_need_main_jump(&tokens) {
+        return Assembler::new(tokens, false).assemble();
+    }
+    match Assembler::new(tokens.clone(), true).assemble() {
+        Ok(assembled) => match assembled.symbols.get(MAIN_LABEL) {
+            Some(&main) if main != PROGRAM_START.offset(2) => Ok(assembled),
+            // Without a main there's nothing to jump to, and if it's first there's no need
+            _ => Assembler::new(tokens, false).assemble(),
+        },
+        // The jump shifts everything after it, which may be all that's wrong
+        Err(error) => match Assembler::new(tokens, false).assemble() {
+            Ok(assembled) if assembled.symbols.get(MAIN_LABEL) == Some(&PROGRAM_START) => {
+                Ok(assembled)
+            }
+            _ => Err(error),
+        },
+    }
+}
+
+/// Whether `main` is defined anywhere other than right at the start
+fn may_need_main_jump(tokens: &[Token]) -> bool {
+    tokens
+        .windows(2)
+        .position(|pair| pair[0].text == ":" && pair[1].text == MAIN_LABEL)
+        .is_some_and(|index| index != 0)
+}
+
+/// An operand which may name a label defined later on
+enum Operand {
+    Known(i64),
+    Label(String),
+}
+
+/// An address to fill in with a label once it's defined
+struct Fixup {
+    address: usize,
+    label: String,
+    line: usize,
+    /// Whether the whole 16 bit word is the label, rather than the low 12 bits
+    wide: bool,
+}
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// A structured control flow statement which hasn't been closed yet
+enum Block {
+    /// An `if ... begin`, with the jump past it when the condition is false
+    If { jump: usize },
+    /// An `else`, with the jump past it from the end of the `if`
+    Else { jump: usize },
+    /// A `loop`, with the jumps out of it from its `while`s
+    Loop { start: usize, breaks: Vec<usize> },
+}
+
+struct Assembler {
+    tokens: VecDeque<Token>,
+    line: usize,
+    rom: Vec<u8>,
+    here: usize,
+    jump_to_main: bool,
+    labels: BTreeMap<String, usize>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    /// How many tokens macros have expanded to so far
+    macro_tokens: usize,
+    blocks: Vec<(Block, usize)>,
+    fixups: Vec<Fixup>,
+}
+
+impl Assembler {
+    fn new(tokens: Vec<Token>, jump_to_main: bool) -> Self {
+        Assembler {
+            tokens: tokens.into(),
+            line: 1,
+            rom: Vec::new(),
+            here: usize::from(PROGRAM_START.0),
+            jump_to_main,
+            labels: BTreeMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            macro_tokens: 0,
+            blocks: Vec::new(),
+            fixups: Vec::new(),
+        }
+    }
+
+    fn assemble(mut self) -> Result<Assembled, AssembleError> {
+        self.statements().map_err(|kind| AssembleError {
+            line: self.line,
+            kind,
+        })?;
+        if let Some(&(_, line)) = self.blocks.last() {
+            return Err(AssembleError {
+                line,
+                kind: AssembleErrorKind::UnclosedBlock,
+            });
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            self.resolve(&fixup).map_err(|kind| AssembleError {
+                line: fixup.line,
+                kind,
+            })?;
+        }
+        if let (true, Some(&main)) = (self.jump_to_main, self.labels.get(MAIN_LABEL)) {
+            self.patch_jump(usize::from(PROGRAM_START.0), main)
+                .map_err(|kind| AssembleError {
+                    line: self.line,
+                    kind,
+                })?;
+        }
+        let symbols = self
+            .labels
+            .iter()
+            .map(|(name, &address)| Ok((name.clone(), Address(address_word(address)?))))
+            .collect::<Result<_, AssembleErrorKind>>()
+            .map_err(|kind| AssembleError {
+                line: self.line,
+                kind,
+            })?;
+        Ok(Assembled {
+            rom: self.rom,
+            symbols,
+        })
+    }
+
+    fn statements(&mut self) -> Result<(), AssembleErrorKind> {
+        if self.jump_to_main {
+            self.placeholder_jump()?;
+        }
+        while !self.tokens.is_empty() {
+            self.statement()?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn statement(&mut self) -> Result<(), AssembleErrorKind> {
+        let token = self.next()?;
+        match token.as_str() {
+            ":" => {
+                let name = self.next()?;
+                self.define(&name)?;
+                self.labels.insert(name, self.here);
+            }
+            ":const" => {
+                let name = self.next()?;
+                self.define(&name)?;
+                #[allow(clippy::cast_precision_loss)]
+                let value = self.number()? as f64;
+                self.constants.insert(name, value);
+            }
+            ":calc" => {
+                let name = self.next()?;
+                self.define(&name)?;
+                self.expect("{")?;
+                let value = self.expression()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":macro" => self.define_macro()?,
+            ":org" => {
+                let address = self.number()?;
+                self.here = usize::try_from(address)
+                    .ok()
+                    .filter(|address| {
+                        (usize::from(PROGRAM_START.0)..XO_CHIP_MEMORY_SIZE).contains(address)
+                    })
+                    .ok_or(AssembleErrorKind::ValueOutOfRange(address))?;
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit(byte)?;
+            }
+            ":pointer" => match self.operand()? {
+                Operand::Known(value) => self.emit_word(to_word(value)?)?,
+                Operand::Label(label) => self.emit_fixup(0, label, true)?,
+            },
+            ":call" => self.address_instruction(|address| Instruction::Call { address })?,
+            "return" | ";" => self.instruction(Instruction::Return)?,
+            "clear" => self.instruction(Instruction::ClearScreen)?,
+            "exit" => self.instruction(Instruction::Exit)?,
+            "lores" => self.instruction(Instruction::LowRes)?,
+            "hires" => self.instruction(Instruction::HighRes)?,
+            "scroll-left" => self.instruction(Instruction::ScrollLeft)?,
+            "scroll-right" => self.instruction(Instruction::ScrollRight)?,
+            "audio" => self.instruction(Instruction::LoadAudioPattern)?,
+            "scroll-down" => {
+                let rows = self.small()?;
+                self.instruction(Instruction::ScrollDown { rows })?;
+            }
+            "scroll-up" => {
+                let rows = self.small()?;
+                self.instruction(Instruction::ScrollUp { rows })?;
+            }
+            "plane" => {
+                let planes = self.small()?;
+                self.instruction(Instruction::SelectPlanes { planes })?;
+            }
+            "bcd" => {
+                let register_num = self.register()?;
+                self.instruction(Instruction::BCDRegister { register_num })?;
+            }
+            "save" | "load" => {
+                let x_reg_num = self.register()?;
+                let instruction = if self.peek() == Some("-") {
+                    self.next()?;
+                    let y_reg_num = self.register()?;
+                    if token == "save" {
+                        Instruction::SaveRegisterRange {
+                            x_reg_num,
+                            y_reg_num,
+                        }
+                    } else {
+                        Instruction::LoadRegisterRange {
+                            x_reg_num,
+                            y_reg_num,
+                        }
+                    }
+                } else if token == "save" {
+                    Instruction::SaveRegistersToMem {
+                        max_reg_num: x_reg_num,
+                    }
+                } else {
+                    Instruction::LoadRegistersFromMem {
+                        max_reg_num: x_reg_num,
+                    }
+                };
+                self.instruction(instruction)?;
+            }
+            "saveflags" => {
+                let max_reg_num = self.register()?;
+                self.instruction(Instruction::SaveRegistersToFlags { max_reg_num })?;
+            }
+            "loadflags" => {
+                let max_reg_num = self.register()?;
+                self.instruction(Instruction::LoadRegistersFromFlags { max_reg_num })?;
+            }
+            "sprite" => {
+                let x_reg_num = self.register()?;
+                let y_reg_num = self.register()?;
+                let sprite_length = self.small()?;
+                self.instruction(Instruction::Draw {
+                    x_reg_num,
+                    y_reg_num,
+                    sprite_length,
+                })?;
+            }
+            "jump" => self.address_instruction(|address| Instruction::JumpTo { address })?,
+            "jump0" => {
+                self.address_instruction(|address| Instruction::JumpWithOffset { address })?;
+            }
+            "native" => self.address_instruction(|address| Instruction::Sys { address })?,
+            "delay" => {
+                self.expect(":=")?;
+                let register_num = self.register()?;
+                self.instruction(Instruction::SetDelayTimer { register_num })?;
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+                let register_num = self.register()?;
+                self.instruction(Instruction::SetSoundTimer { register_num })?;
+            }
+            "pitch" => {
+                self.expect(":=")?;
+                let register_num = self.register()?;
+                self.instruction(Instruction::SetPitch { register_num })?;
+            }
+            "i" => self.i_statement()?,
+            "if" => self.if_statement()?,
+            "else" => {
+                let Some((Block::If { jump }, line)) = self.blocks.pop() else {
+                    return Err(AssembleErrorKind::UnmatchedBlock(token));
+                };
+                let end_jump = self.placeholder_jump()?;
+                self.patch_jump(jump, self.here)?;
+                self.blocks.push((Block::Else { jump: end_jump }, line));
+            }
+            "end" => {
+                let Some((Block::If { jump } | Block::Else { jump }, _)) = self.blocks.pop() else {
+                    return Err(AssembleErrorKind::UnmatchedBlock(token));
+                };
+                self.patch_jump(jump, self.here)?;
+            }
+            "loop" => self.blocks.push((
+                Block::Loop {
+                    start: self.here,
+                    breaks: Vec::new(),
+                },
+                self.line,
+            )),
+            "while" => {
+                if !self
+                    .blocks
+                    .iter()
+                    .any(|(block, _)| matches!(block, Block::Loop { .. }))
+                {
+                    return Err(AssembleErrorKind::UnmatchedBlock(token));
+                }
+                self.conditional(true)?;
+                let jump = self.placeholder_jump()?;
+                if let Some((Block::Loop { breaks, .. }, _)) = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|(block, _)| matches!(block, Block::Loop { .. }))
+                {
+                    breaks.push(jump);
+                }
+            }
+            "again" => {
+                let Some((Block::Loop { start, breaks }, _)) = self.blocks.pop() else {
+                    return Err(AssembleErrorKind::UnmatchedBlock(token));
+                };
+                self.instruction(Instruction::JumpTo {
+                    address: Address(address_word(start)?),
+                })?;
+                for jump in breaks {
+                    self.patch_jump(jump, self.here)?;
+                }
+            }
+            _ => {
+                if let Some(x_reg_num) = self.register_number(&token) {
+                    self.register_statement(x_reg_num)?;
+                } else if self.macros.contains_key(&token) {
+                    self.expand_macro(&token)?;
+                } else if let Some(&address) = self.labels.get(&token) {
+                    // A label on its own calls it
+                    self.instruction(Instruction::Call {
+                        address: Address(address_word(address)?),
+                    })?;
+                } else if let Some(value) = self.value(&token) {
+                    // While a number on its own is a byte of data
+                    self.emit(to_byte(value)?)?;
+                } else if is_name(&token) {
+                    let call = u16::try_from(Instruction::Call {
+                        address: Address(0),
+                    })?;
+                    self.emit_fixup(call, token, false)?;
+                } else {
+                    return Err(AssembleErrorKind::UnexpectedToken(token));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<String, AssembleErrorKind> {
+        let token = self
+            .tokens
+            .pop_front()
+            .ok_or(AssembleErrorKind::UnexpectedEnd)?;
+        self.line = token.line;
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, index: usize) -> Option<&str> {
+        self.tokens.get(index).map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AssembleErrorKind> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(AssembleErrorKind::UnexpectedToken(token))
+        }
+    }
+
+    /// Checks `name` is free to be given to a label or constant
+    fn define(&self, name: &str) -> Result<(), AssembleErrorKind> {
+        if !is_name(name) || self.register_number(name).is_some() {
+            return Err(AssembleErrorKind::UnexpectedToken(name.to_owned()));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(AssembleErrorKind::Redefined(name.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// The value of a number, constant or label already defined
+    fn value(&self, token: &str) -> Option<i64> {
+        #[allow(clippy::cast_possible_truncation)]
+        tokens::number(token)
+            .or_else(|| self.constants.get(token).map(|&value| value as i64))
+            .or_else(|| {
+                self.labels
+                    .get(token)
+                    .and_then(|&address| i64::try_from(address).ok())
+            })
+    }
+
+    fn operand(&mut self) -> Result<Operand, AssembleErrorKind> {
+        let token = self.next()?;
+        if token == "{" {
+            #[allow(clippy::cast_possible_truncation)]
+            return Ok(Operand::Known(self.expression()? as i64));
+        }
+        if let Some(value) = self.value(&token) {
+            return Ok(Operand::Known(value));
+        }
+        if is_name(&token) && self.register_number(&token).is_none() {
+            return Ok(Operand::Label(token));
+        }
+        Err(AssembleErrorKind::ExpectedNumber(token))
+    }
+
+    /// A value which must be known already, as it's not an address
+    fn number(&mut self) -> Result<i64, AssembleErrorKind> {
+        match self.operand()? {
+            Operand::Known(value) => Ok(value),
+            Operand::Label(label) => Err(AssembleErrorKind::ExpectedNumber(label)),
+        }
+    }
+
+    /// A byte, where negative numbers stand for their two's complement
+    fn byte(&mut self) -> Result<u8, AssembleErrorKind> {
+        to_byte(self.number()?)
+    }
+
+    /// A positive number, which may not fit the nibble it's meant for
+    fn small(&mut self) -> Result<u8, AssembleErrorKind> {
+        let value = self.number()?;
+        u8::try_from(value).map_err(|_| AssembleErrorKind::ValueOutOfRange(value))
+    }
+
+    fn register(&mut self) -> Result<u8, AssembleErrorKind> {
+        let token = self.next()?;
+        self.register_number(&token)
+            .ok_or(AssembleErrorKind::ExpectedRegister(token))
+    }
+
+    /// The register named by `token`, either as `v0` to `vf` or by an alias
+    fn register_number(&self, token: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(token) {
+            return Some(register);
+        }
+        match token.as_bytes() {
+            [b'v' | b'V', digit] => char::from(*digit)
+                .to_digit(16)
+                .and_then(|digit| u8::try_from(digit).ok()),
+            _ => None,
+        }
+    }
+
+    fn peek_register(&self) -> Option<u8> {
+        self.peek().and_then(|token| self.register_number(token))
+    }
+
+    /// Evaluates the expression up to the closing brace, the opening brace already read
+    fn expression(&mut self) -> Result<f64, AssembleErrorKind> {
+        let mut expression = Vec::new();
+        loop {
+            match self.next()? {
+                token if token == "}" => break,
+                token => expression.push(token),
+            }
+        }
+        let expression: Vec<&str> = expression.iter().map(String::as_str).collect();
+        let here = self.here;
+        #[allow(clippy::cast_precision_loss)]
+        let value = calc::evaluate(&expression, |name| match name {
+            "HERE" => Some(here as f64),
+            name => self.value(name).map(|value| value as f64),
+        })?;
+        Ok(value)
+    }
+
+    /// Reads a `:macro name parameters... { body }`
+    fn define_macro(&mut self) -> Result<(), AssembleErrorKind> {
+        let name = self.next()?;
+        let mut parameters = Vec::new();
+        loop {
+            match self.next()? {
+                token if token == "{" => break,
+                token => parameters.push(token),
+            }
+        }
+        let mut body = Vec::new();
+        let mut depth = 0_usize;
+        loop {
+            let token = self
+                .tokens
+                .pop_front()
+                .ok_or(AssembleErrorKind::UnexpectedEnd)?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 0 => break,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            body.push(token);
+        }
+        self.macros.insert(name, Macro { parameters, body });
+        Ok(())
+    }
+
+    /// Replaces a macro's name and arguments with its body
+    fn expand_macro(&mut self, name: &str) -> Result<(), AssembleErrorKind> {
+        let parameters = self.macros[name].parameters.len();
+        let arguments = (0..parameters)
+            .map(|_| self.next())
+            .collect::<Result<Vec<_>, _>>()?;
+        let definition = &self.macros[name];
+        self.macro_tokens += definition.body.len();
+        if self.macro_tokens > MAX_MACRO_TOKENS {
+            return Err(AssembleErrorKind::MacroRecursion(name.to_owned()));
+        }
+        for token in definition.body.iter().rev() {
+            let text = definition
+                .parameters
+                .iter()
+                .position(|parameter| *parameter == token.text)
+                .map_or_else(|| token.text.clone(), |index| arguments[index].clone());
+            self.tokens.push_front(Token {
+                text,
+                line: token.line,
+            });
+        }
+        Ok(())
+    }
+
+    fn i_statement(&mut self) -> Result<(), AssembleErrorKind> {
+        let operator = self.next()?;
+        match operator.as_str() {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.next()?;
+                    let register_num = self.register()?;
+                    self.instruction(Instruction::LoadISprite { register_num })
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    let register_num = self.register()?;
+                    self.instruction(Instruction::LoadIBigSprite { register_num })
+                }
+                Some("long") => {
+                    self.next()?;
+                    self.emit_word(LONG_LOAD_I_PREFIX)?;
+                    match self.operand()? {
+                        Operand::Known(value) => self.emit_word(to_word(value)?),
+                        Operand::Label(label) => self.emit_fixup(0, label, true),
+                    }
+                }
+                _ => self.address_instruction(|address| Instruction::LoadIImm { imm: address.0 }),
+            },
+            "+=" => {
+                let register_num = self.register()?;
+                self.instruction(Instruction::AddIV { register_num })
+            }
+            _ => Err(AssembleErrorKind::UnexpectedToken(operator)),
+        }
+    }
+
+    fn register_statement(&mut self, x_reg_num: u8) -> Result<(), AssembleErrorKind> {
+        let operator = self.next()?;
+        if let Some(y_reg_num) = self.peek_register() {
+            self.next()?;
+            let instruction = match operator.as_str() {
+                ":=" => Instruction::SetEqual {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                "|=" => Instruction::BitWiseOrEqual {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                "&=" => Instruction::BitWiseAndEqual {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                "^=" => Instruction::BitWiseXorEqual {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                "+=" => Instruction::AddV2 {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                "-=" => Instruction::SubV2 {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                "=-" => Instruction::SubNV2 {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                ">>=" => Instruction::ShiftRight {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                "<<=" => Instruction::ShiftLeft {
+                    x_reg_num,
+                    y_reg_num,
+                },
+                _ => return Err(AssembleErrorKind::UnexpectedToken(operator)),
+            };
+            return self.instruction(instruction);
+        }
+        let reg_num = x_reg_num;
+        let register_num = x_reg_num;
+        let instruction = match (operator.as_str(), self.peek()) {
+            (":=", Some("key")) => {
+                self.next()?;
+                Instruction::WaitForKey { register_num }
+            }
+            (":=", Some("delay")) => {
+                self.next()?;
+                Instruction::LoadVDelayTimer { register_num }
+            }
+            (":=", Some("random")) => {
+                self.next()?;
+                let imm = self.byte()?;
+                Instruction::LoadVRandom { reg_num, imm }
+            }
+            (":=", _) => {
+                let imm = self.byte()?;
+                Instruction::LoadVImm { reg_num, imm }
+            }
+            ("+=", _) => {
+                let imm = self.byte()?;
+                Instruction::AddVImm { reg_num, imm }
+            }
+            ("-=", _) => {
+                let imm = self.byte()?.wrapping_neg();
+                Instruction::AddVImm { reg_num, imm }
+            }
+            _ => return Err(AssembleErrorKind::UnexpectedToken(operator)),
+        };
+        self.instruction(instruction)
+    }
+
+    /// Reads an `if`, which either skips the statement after `then` or is a `begin` block
+    fn if_statement(&mut self) -> Result<(), AssembleErrorKind> {
+        // Key checks are the only conditions without a right hand side
+        let control = match self.peek_at(1) {
+            Some("key" | "-key") => self.peek_at(2),
+            _ => self.peek_at(3),
+        };
+        match control {
+            Some("then") => {
+                self.conditional(false)?;
+                self.expect("then")
+            }
+            Some("begin") => {
+                self.conditional(true)?;
+                self.expect("begin")?;
+                let jump = self.placeholder_jump()?;
+                self.blocks.push((Block::If { jump }, self.line));
+                Ok(())
+            }
+            Some(token) => Err(AssembleErrorKind::UnexpectedToken(token.to_owned())),
+            None => Err(AssembleErrorKind::UnexpectedEnd),
+        }
+    }
+
+    /// Emits a condition, skipping the next instruction when it's false, or when it's true
+    /// if `negated`
+    ///
+    /// Comparisons beyond equality subtract into a temporary register, VF unless aliased with
+    /// `compare-temp`, and check the borrow left in VF.
+    fn conditional(&mut self, negated: bool) -> Result<(), AssembleErrorKind> {
+        let reg_num = self.register()?;
+        let operator = self.next()?;
+        let operator = if negated {
+            negate(&operator)
+                .ok_or(AssembleErrorKind::UnexpectedToken(operator))?
+                .to_owned()
+        } else {
+            operator
+        };
+        match operator.as_str() {
+            "key" => {
+                return self.instruction(Instruction::SkipIfKeyNotPressed {
+                    register_num: reg_num,
+                })
+            }
+            "-key" => {
+                return self.instruction(Instruction::SkipIfKeyPressed {
+                    register_num: reg_num,
+                })
+            }
+            _ => {}
+        }
+        let rhs_register = self.peek_register();
+        if rhs_register.is_some() {
+            self.next()?;
+        }
+        match (operator.as_str(), rhs_register) {
+            ("==", Some(y_reg_num)) => self.instruction(Instruction::SkipIfNotEqualV2 {
+                x_reg_num: reg_num,
+                y_reg_num,
+            }),
+            ("==", None) => {
+                let imm = self.byte()?;
+                self.instruction(Instruction::SkipIfNotEqVImm { reg_num, imm })
+            }
+            ("!=", Some(y_reg_num)) => self.instruction(Instruction::SkipIfEqualV2 {
+                x_reg_num: reg_num,
+                y_reg_num,
+            }),
+            ("!=", None) => {
+                let imm = self.byte()?;
+                self.instruction(Instruction::SkipIfEqVImm { reg_num, imm })
+            }
+            (">" | "<" | ">=" | "<=", _) => {
+                let temp = self.aliases.get("compare-temp").copied().unwrap_or(0xF);
+                let load = match rhs_register {
+                    Some(y_reg_num) => Instruction::SetEqual {
+                        x_reg_num: temp,
+                        y_reg_num,
+                    },
+                    None => Instruction::LoadVImm {
+                        reg_num: temp,
+                        imm: self.byte()?,
+                    },
+                };
+                self.instruction(load)?;
+                // VF is set when the subtraction doesn't borrow
+                self.instruction(if matches!(operator.as_str(), ">" | "<=") {
+                    Instruction::SubV2 {
+                        x_reg_num: temp,
+                        y_reg_num: reg_num,
+                    }
+                } else {
+                    Instruction::SubNV2 {
+                        x_reg_num: temp,
+                        y_reg_num: reg_num,
+                    }
+                })?;
+                self.instruction(if matches!(operator.as_str(), ">" | "<") {
+                    Instruction::SkipIfEqVImm {
+                        reg_num: 0xF,
+                        imm: 1,
+                    }
+                } else {
+                    Instruction::SkipIfNotEqVImm {
+                        reg_num: 0xF,
+                        imm: 1,
+                    }
+                })
+            }
+            _ => Err(AssembleErrorKind::UnexpectedToken(operator)),
+        }
+    }
+
+    fn instruction(&mut self, instruction: Instruction) -> Result<(), AssembleErrorKind> {
+        let opcode = u16::try_from(instruction)?;
+        self.emit_word(opcode)
+    }
+
+    /// Emits an instruction taking an address, which may be a label defined later on
+    fn address_instruction(
+        &mut self,
+        instruction: fn(Address) -> Instruction,
+    ) -> Result<(), AssembleErrorKind> {
+        match self.operand()? {
+            Operand::Known(value) => self.instruction(instruction(Address(to_word(value)?))),
+            Operand::Label(label) => {
+                let opcode = u16::try_from(instruction(Address(0)))?;
+                self.emit_fixup(opcode, label, false)
+            }
+        }
+    }
+
+    fn emit(&mut self, byte: u8) -> Result<(), AssembleErrorKind> {
+        if self.here >= XO_CHIP_MEMORY_SIZE {
+            return Err(AssembleErrorKind::ProgramTooLarge);
+        }
+        let offset = self.here - usize::from(PROGRAM_START.0);
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit_word(&mut self, word: u16) -> Result<(), AssembleErrorKind> {
+        let [high, low] = word.to_be_bytes();
+        self.emit(high)?;
+        self.emit(low)
+    }
+
+    /// Emits `word`, to have `label` filled into it at the end
+    fn emit_fixup(
+        &mut self,
+        word: u16,
+        label: String,
+        wide: bool,
+    ) -> Result<(), AssembleErrorKind> {
+        self.fixups.push(Fixup {
+            address: self.here,
+            label,
+            line: self.line,
+            wide,
+        });
+        self.emit_word(word)
+    }
+
+    fn resolve(&mut self, fixup: &Fixup) -> Result<(), AssembleErrorKind> {
+        let address = *self
+            .labels
+            .get(&fixup.label)
+            .ok_or_else(|| AssembleErrorKind::UndefinedLabel(fixup.label.clone()))?;
+        let address = address_word(address)?;
+        let offset = fixup.address - usize::from(PROGRAM_START.0);
+        let opcode = u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]]);
+        let opcode = if fixup.wide {
+            address
+        } else if address > 0xFFF {
+            return Err(InstructionEncodeError::AddressOutOfRange(address).into());
+        } else {
+            opcode | address
+        };
+        self.rom[offset..offset + 2].copy_from_slice(&opcode.to_be_bytes());
+        Ok(())
+    }
+
+    /// Emits a jump to be pointed somewhere later on, returning where it is
+    fn placeholder_jump(&mut self) -> Result<usize, AssembleErrorKind> {
+        let jump = self.here;
+        self.instruction(Instruction::JumpTo {
+            address: Address(0),
+        })?;
+        Ok(jump)
+    }
+
+    fn patch_jump(&mut self, jump: usize, target: usize) -> Result<(), AssembleErrorKind> {
+        let opcode = u16::try_from(Instruction::JumpTo {
+            address: Address(address_word(target)?),
+        })?;
+        let offset = jump - usize::from(PROGRAM_START.0);
+        self.rom[offset..offset + 2].copy_from_slice(&opcode.to_be_bytes());
+        Ok(())
+    }
+}
+
+/// The condition which is true exactly when `operator` is false
+fn negate(operator: &str) -> Option<&'static str> {
+    Some(match operator {
+        "==" => "!=",
+        "!=" => "==",
+        "key" => "-key",
+        "-key" => "key",
+        "<" => ">=",
+        ">=" => "<",
+        ">" => "<=",
+        "<=" => ">",
+        _ => return None,
+    })
+}
+
+/// Whether `token` can name a label or constant
+fn is_name(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+fn to_word(value: i64) -> Result<u16, AssembleErrorKind> {
+    u16::try_from(value).map_err(|_| AssembleErrorKind::ValueOutOfRange(value))
+}
+
+/// An address in memory as a word, which is only too large at the very end of memory
+fn address_word(address: usize) -> Result<u16, AssembleErrorKind> {
+    u16::try_from(address).map_err(|_| AssembleErrorKind::ProgramTooLarge)
+}
+
+fn to_byte(value: i64) -> Result<u8, AssembleErrorKind> {
+    if (-128..=255).contains(&value) {
+        Ok(value.to_le_bytes()[0])
+    } else {
+        Err(AssembleErrorKind::ValueOutOfRange(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        cpu::{analysis::Analysis, disassembler::Syntax},
+        platform::Platform,
+    };
+
+    fn rom(source: &str) -> Vec<u8> {
+        assemble(source).unwrap().rom
+    }
+
+    fn error(source: &str) -> (usize, AssembleErrorKind) {
+        let error = assemble(source).unwrap_err();
+        (error.line, error.kind)
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(
+            rom("clear v1 := 0x20 v1 -= 1 v2 =- v1 i := hex v2 sprite v1 v2 5 save v0 - v3"),
+            [0x00, 0xE0, 0x61, 0x20, 0x71, 0xFF, 0x82, 0x17, 0xF2, 0x29, 0xD1, 0x25, 0x50, 0x32]
+        );
+        assert_eq!(
+            rom("v3 := random 0b1111 v0 := key delay := v0 i := long 0x1234 plane 3"),
+            [0xC3, 0x0F, 0xF0, 0x0A, 0xF0, 0x15, 0xF0, 0x00, 0x12, 0x34, 0xF3, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_labels_and_main() {
+        let assembled = assemble(
+            ": sprite-data 0xF0 0x90
+             : main
+               i := sprite-data
+               draw
+               jump main
+             : draw sprite v0 v0 2 return",
+        )
+        .unwrap();
+        assert_eq!(
+            assembled.rom,
+            [0x12, 0x04, 0xF0, 0x90, 0xA2, 0x02, 0x22, 0x0A, 0x12, 0x04, 0xD0, 0x02, 0x00, 0xEE]
+        );
+        assert_eq!(assembled.symbols["sprite-data"], Address(0x202));
+        assert_eq!(assembled.symbols["draw"], Address(0x20A));
+
+        // A jump to main is only added when main isn't first, moving everything after it
+        let shifted = ":byte { HERE - 0x101 }";
+        assert_eq!(rom(shifted), [0xFF]);
+        let assembled = assemble(&format!(":alias x v1\n: main\n{shifted}")).unwrap();
+        assert_eq!(assembled.rom, [0xFF]);
+        assert_eq!(assembled.symbols["main"], Address(0x200));
+        assert_eq!(
+            error(&format!("clear\n: main\n{shifted}")),
+            (3, AssembleErrorKind::ValueOutOfRange(0x103))
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(rom("if v0 == 1 then v1 := 2"), [0x40, 0x01, 0x61, 0x02]);
+        assert_eq!(
+            rom("if v0 != v1 begin clear else return end"),
+            [0x90, 0x10, 0x12, 0x08, 0x00, 0xE0, 0x12, 0x0A, 0x00, 0xEE]
+        );
+        assert_eq!(
+            rom("loop v0 += 1 while v0 != 10 again"),
+            [0x70, 0x01, 0x40, 0x0A, 0x12, 0x08, 0x12, 0x00]
+        );
+        assert_eq!(
+            rom("if v2 > 5 then clear"),
+            [0x6F, 0x05, 0x8F, 0x25, 0x3F, 0x01, 0x00, 0xE0]
+        );
+    }
+
+    #[test]
+    fn test_directives() {
+        assert_eq!(
+            rom(":const SPEED 3
+                 :alias x v4
+                 :calc DOUBLE { SPEED * 2 + 1 } # 3 * (2 + 1), evaluated right to left
+                 :macro bump register amount { register += amount }
+                 bump x DOUBLE
+                 :org 0x208 :byte { HERE - 0x200 } :pointer end
+                 : end"),
+            [0x74, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x02, 0x0B]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error("v0 := 1\nsprite v0 v16 1"),
+            (2, AssembleErrorKind::ExpectedRegister("v16".to_owned()))
+        );
+        assert_eq!(
+            error("clear\njump nowhere"),
+            (2, AssembleErrorKind::UndefinedLabel("nowhere".to_owned()))
+        );
+        assert_eq!(
+            error("v0 := 256"),
+            (1, AssembleErrorKind::ValueOutOfRange(256))
+        );
+        assert_eq!(
+            error("sprite v0 v1 16"),
+            (
+                1,
+                AssembleErrorKind::Encode(InstructionEncodeError::NibbleOutOfRange(16))
+            )
+        );
+        assert_eq!(error("loop\nclear"), (1, AssembleErrorKind::UnclosedBlock));
+        assert_eq!(
+            error("end"),
+            (1, AssembleErrorKind::UnmatchedBlock("end".to_owned()))
+        );
+        assert_eq!(
+            error(":macro m { m }\n: main m"),
+            (1, AssembleErrorKind::MacroRecursion("m".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_reassembles_analysis_listing() {
+        let program = [
+            0xA2, 0x0E, 0x60, 0x00, 0xD0, 0x05, 0x22, 0x14, 0x3F, 0x01, 0x12, 0x08, 0xF0, 0x00,
+            0xF0, 0x90, 0x90, 0x90, 0xF0, 0x00, 0xE0, 0x9E, 0x80, 0x17, 0xF1, 0x55, 0x00, 0xEE,
+        ];
+        let listing = Analysis::new(&program, PROGRAM_START, Platform::Chip8).listing(Syntax::Octo);
+        assert_eq!(assemble(&listing).unwrap().rom, program);
+    }
+}
@@ -0,0 +1,60 @@
+/// A whitespace separated word of Octo source, with the line it's on
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub struct Token {
+    pub text: String,
+    /// The line number, counting from 1
+    pub line: usize,
+}
+
+/// Splits Octo source into tokens, dropping `#` comments
+/// ```
+/// # use eoxchip8::assembler::tokens::tokenize;
+/// let tokens = tokenize("v0 := 1 # set up\nloop again");
+/// let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+/// assert_eq!(texts, ["v0", ":=", "1", "loop", "again"]);
+/// assert_eq!(tokens[3].line, 2);
+/// ```
+#[must_use]
+pub fn tokenize(source: &str) -> Vec<Token> {
+    source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            line.split_whitespace()
+                .take_while(|word| !word.starts_with('#'))
+                .map(move |word| Token {
+                    text: word.to_owned(),
+                    line: index + 1,
+                })
+        })
+        .collect()
+}
+
+/// Parses a number literal, in decimal, 0x-prefixed hex or 0b-prefixed binary, which may be
+/// negative
+/// ```
+/// # use eoxchip8::assembler::tokens::number;
+/// assert_eq!(number("-12"), Some(-12));
+/// assert_eq!(number("0xFF"), Some(255));
+/// assert_eq!(number("0b101"), Some(5));
+/// assert_eq!(number("v0"), None);
+/// ```
+#[must_use]
+pub fn number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2)
+    } else {
+        digits.parse()
+    }
+    .ok()?;
+    Some(if negative { -value } else { value })
+}
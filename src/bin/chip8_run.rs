@@ -1,51 +1,600 @@
 use std::{
     fs::File,
-    io::Read,
-    path::PathBuf,
-    time::{Duration, Instant},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
 
-use clap::Parser;
-use eoxchip8::core::cpu::main::Executor;
-use log::error;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use eoxchip8::{
+    assembler::octo::assemble,
+    core::{
+        audio::{
+            max_beep_frequency, Beeper, DEFAULT_BEEP_FREQUENCY, DEFAULT_BEEP_VOLUME,
+            FRAMES_PER_SECOND,
+        },
+        clock::{Clock, ScaledClock, VirtualClock, WallClock},
+        cpu::{
+            analysis::Analysis,
+            disassembler::{Disassembler, Syntax},
+            main::{ExecutionError, Executor, RunState},
+            quirks::{MemoryIncrement, QuirkPreset, Quirks},
+            stack::StackLimit,
+            timing::TimingModel,
+        },
+        font::{FontSet, DEFAULT_FONT_ADDRESS},
+        memory::{Address, Chip8Display, PROGRAM_START},
+        platform::Platform,
+        rng::XorShiftRng,
+        rpl::FileRplStorage,
+        scheduler::{CycleBudget, FramePacer},
+        wav::WavWriter,
+    },
+};
+
+/// Instructions run each second with uniform timing, unless configured otherwise
+const DEFAULT_OPCODES_PER_SECOND: u32 = 700;
 
-#[derive(Debug, Parser, PartialEq, Eq, PartialOrd, Ord)]
-#[command(author, version, about)]
+#[derive(Debug, Parser, PartialEq, PartialOrd)]
+#[command(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Chip8RunArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(short, long, required = true)]
+    program_path: Option<PathBuf>,
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+/// Options for running a program, shared by running a ROM and assembling one to run
+#[derive(Debug, Args, PartialEq, PartialOrd)]
+struct RunArgs {
+    /// Instruction set the program is written for
+    #[arg(long, value_enum, default_value_t = PlatformArg::Chip8)]
+    platform: PlatformArg,
+    #[command(flatten)]
+    quirks: QuirkArgs,
+    /// Instructions to run each second, 700 unless given, not used with VIP timing
+    #[arg(short, long)]
+    opcodes_per_second: Option<u32>,
+    /// How instruction costs are counted, VIP timing runs at the VIP's own speed
+    #[arg(long, value_enum, default_value_t = TimingArg::Uniform)]
+    timing: TimingArg,
+    /// Seed for the random number generator, for reproducible runs
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Hex digit font to load into the interpreter area
+    #[arg(long, value_enum, default_value_t = FontArg::Standard)]
+    font: FontArg,
+    /// Address the font is loaded at, in decimal or 0x-prefixed hex, leaving it below 0x200
+    #[arg(long, value_parser = parse_address, default_value_t = DEFAULT_FONT_ADDRESS.0)]
+    font_address: u16,
+    /// Keep the call stack in RAM at this address, in decimal or 0x-prefixed hex, as the VIP
+    /// did at 0xEA0
+    #[arg(long, value_parser = parse_address)]
+    stack_address: Option<u16>,
+    /// File keeping the SUPER-CHIP RPL flags, defaults to the program path with `.rpl` appended
+    #[arg(long)]
+    rpl_path: Option<PathBuf>,
+    /// Record the program's audio output to a WAV file
+    #[arg(long)]
+    wav_output: Option<PathBuf>,
+    /// Sample rate of recorded audio, in Hz
+    #[arg(long, default_value_t = 44100)]
+    sample_rate: u32,
+    /// Frequency of the beeper's tone, in Hz, below half the sample rate
+    #[arg(long, default_value_t = DEFAULT_BEEP_FREQUENCY)]
+    beep_frequency: u32,
+    /// Volume of the beeper, out of 255
+    #[arg(long, default_value_t = DEFAULT_BEEP_VOLUME)]
+    beep_volume: u8,
+    /// Run as fast as possible without printing the display, for automated testing
+    ///
+    /// Exits with 0 once the program exits, or jumps to itself with both timers run down, 3 when
+    /// a limit is reached, 4 on an execution error and 5 when the program waits for a key.
+    #[arg(long)]
+    headless: bool,
+    /// Stop after this many cycles, instructions with uniform timing or machine cycles with VIP
+    #[arg(long)]
+    max_cycles: Option<u64>,
+    /// Stop after this many 60 Hz frames
+    #[arg(long)]
+    max_frames: Option<u64>,
+    /// Write the final contents of the display to a file, or to stdout when given `-`
+    #[arg(long)]
+    dump_screen: Option<PathBuf>,
+    /// Multiplier on the speed of emulated time, above 1 to fast-forward and below to slow down
+    #[arg(long, value_parser = parse_speed, default_value_t = 1.0)]
+    speed: f64,
+}
+
+#[derive(Debug, Subcommand, PartialEq, PartialOrd)]
+enum Command {
+    /// List the instructions of a program with their addresses and opcodes
+    Disasm(DisasmArgs),
+    /// Follow a program's control flow to separate its code from its data, listing it with
+    /// labels so it can be reassembled
+    Analyze(DisasmArgs),
+    /// Assemble a program written for the Octo assembler into a ROM
+    Asm(Box<AsmArgs>),
+}
+
+#[derive(Debug, Args, PartialEq, Eq, PartialOrd, Ord)]
+struct DisasmArgs {
     #[arg(short, long)]
     program_path: PathBuf,
-    // Use the original Chip-8 shift with Vx = Vy
+    /// Instruction set the program is written for
+    #[arg(long, value_enum, default_value_t = PlatformArg::Chip8)]
+    platform: PlatformArg,
+    /// Assembly language to list instructions in
+    #[arg(long, value_enum, default_value_t = SyntaxArg::Cowgod)]
+    syntax: SyntaxArg,
+}
+
+#[derive(Debug, Args, PartialEq, PartialOrd)]
+struct AsmArgs {
     #[arg(short, long)]
-    legacy_shift: bool,
-    #[arg(short, long, default_value_t = 700)]
-    opcodes_per_second: u32,
+    source_path: PathBuf,
+    /// Where to write the ROM, defaults to the source path with a `ch8` extension
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Write the address of every label to a file
+    #[arg(long)]
+    symbols: Option<PathBuf>,
+    /// Run the ROM once it's assembled
+    #[arg(long)]
+    run: bool,
+    #[command(flatten)]
+    run_args: RunArgs,
 }
 
-fn main() {
-    env_logger::init();
+/// Quirk selection, a preset with individual overrides applied on top
+#[derive(Debug, Args, PartialEq, Eq, PartialOrd, Ord)]
+struct QuirkArgs {
+    /// Interpreter whose quirks to start from, instead of the platform's usual quirks
+    #[arg(short, long, value_enum)]
+    quirks: Option<PresetArg>,
+    /// Shift VY into VX for 8XY6 and 8XYE
+    #[arg(long)]
+    shift_uses_vy: Option<bool>,
+    /// Reset VF after 8XY1, 8XY2 and 8XY3
+    #[arg(long)]
+    logic_resets_vf: Option<bool>,
+    /// How FX55 and FX65 change I
+    #[arg(long, value_enum)]
+    memory_increment: Option<MemoryIncrementArg>,
+    /// Wrap sprites around the edges of the display instead of clipping them
+    #[arg(long)]
+    wrap_sprites: Option<bool>,
+    /// Use the CHIP-48 BXNN jump, offsetting by VX instead of V0
+    #[arg(long)]
+    jump_uses_vx: Option<bool>,
+    /// Wait for the vertical blank before drawing sprites
+    #[arg(long)]
+    display_wait: Option<bool>,
+    /// Set VF when FX1E pushes I past 0xFFF
+    #[arg(long)]
+    add_i_overflow_sets_vf: Option<bool>,
+    /// How deep calls may nest, a number of calls or `unlimited`
+    #[arg(long, value_parser = parse_stack_limit)]
+    stack_limit: Option<StackLimit>,
+}
+
+impl QuirkArgs {
+    fn quirks(&self, platform: Platform) -> Quirks {
+        let mut quirks = self.quirks.map_or_else(
+            || platform.default_quirks(),
+            |preset| QuirkPreset::from(preset).quirks(),
+        );
+        let overrides = [
+            (self.shift_uses_vy, &mut quirks.shift_uses_vy),
+            (self.logic_resets_vf, &mut quirks.logic_resets_vf),
+            (self.wrap_sprites, &mut quirks.wrap_sprites),
+            (self.jump_uses_vx, &mut quirks.jump_uses_vx),
+            (self.display_wait, &mut quirks.display_wait),
+            (
+                self.add_i_overflow_sets_vf,
+                &mut quirks.add_i_overflow_sets_vf,
+            ),
+        ];
+        for (value, quirk) in overrides {
+            if let Some(value) = value {
+                *quirk = value;
+            }
+        }
+        if let Some(memory_increment) = self.memory_increment {
+            quirks.memory_increment = memory_increment.into();
+        }
+        if let Some(stack_limit) = self.stack_limit {
+            quirks.stack_limit = stack_limit;
+        }
+        quirks
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum PlatformArg {
+    Chip8,
+    Schip,
+    Xochip,
+}
+
+impl From<PlatformArg> for Platform {
+    fn from(platform: PlatformArg) -> Self {
+        match platform {
+            PlatformArg::Chip8 => Platform::Chip8,
+            PlatformArg::Schip => Platform::SuperChip,
+            PlatformArg::Xochip => Platform::XoChip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum PresetArg {
+    Vip,
+    Chip48,
+    Schip10,
+    Schip11,
+    Xochip,
+}
+
+impl From<PresetArg> for QuirkPreset {
+    fn from(preset: PresetArg) -> Self {
+        match preset {
+            PresetArg::Vip => QuirkPreset::CosmacVip,
+            PresetArg::Chip48 => QuirkPreset::Chip48,
+            PresetArg::Schip10 => QuirkPreset::SuperChip10,
+            PresetArg::Schip11 => QuirkPreset::SuperChip11,
+            PresetArg::Xochip => QuirkPreset::XoChip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum MemoryIncrementArg {
+    Unchanged,
+    X,
+    XPlusOne,
+}
+
+impl From<MemoryIncrementArg> for MemoryIncrement {
+    fn from(memory_increment: MemoryIncrementArg) -> Self {
+        match memory_increment {
+            MemoryIncrementArg::Unchanged => MemoryIncrement::Unchanged,
+            MemoryIncrementArg::X => MemoryIncrement::ByX,
+            MemoryIncrementArg::XPlusOne => MemoryIncrement::ByXPlusOne,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum TimingArg {
+    Uniform,
+    Vip,
+}
+
+impl From<TimingArg> for TimingModel {
+    fn from(timing: TimingArg) -> Self {
+        match timing {
+            TimingArg::Uniform => TimingModel::Uniform,
+            TimingArg::Vip => TimingModel::CosmacVip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum SyntaxArg {
+    Cowgod,
+    Octo,
+}
+
+impl From<SyntaxArg> for Syntax {
+    fn from(syntax: SyntaxArg) -> Self {
+        match syntax {
+            SyntaxArg::Cowgod => Syntax::Cowgod,
+            SyntaxArg::Octo => Syntax::Octo,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum FontArg {
+    Standard,
+    Vip,
+    Dream6800,
+    Eti660,
+}
+
+impl From<FontArg> for FontSet {
+    fn from(font: FontArg) -> Self {
+        match font {
+            FontArg::Standard => FontSet::Standard,
+            FontArg::Vip => FontSet::CosmacVip,
+            FontArg::Dream6800 => FontSet::Dream6800,
+            FontArg::Eti660 => FontSet::Eti660,
+        }
+    }
+}
+
+/// Why a run of `chip8_run` ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunOutcome {
+    Exited,
+    LimitReached,
+    Faulted,
+    WaitingForKey,
+}
+
+impl RunOutcome {
+    fn exit_code(self) -> ExitCode {
+        match self {
+            RunOutcome::Exited => ExitCode::SUCCESS,
+            RunOutcome::LimitReached => ExitCode::from(3),
+            RunOutcome::Faulted => ExitCode::from(4),
+            RunOutcome::WaitingForKey => ExitCode::from(5),
+        }
+    }
+}
 
-    let args = Chip8RunArgs::parse();
+fn parse_address(arg: &str) -> Result<u16, std::num::ParseIntError> {
+    match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => arg.parse(),
+    }
+}
 
-    let mut rom = File::open(args.program_path).unwrap();
+fn print_crash_report(error: &ExecutionError) {
+    eprintln!("Program crashed: {}", error.kind);
+    eprintln!("  PC:          {:#05x}", error.pc.0);
+    match error.opcode {
+        Some(opcode) => eprintln!("  Opcode:      {opcode:#06x}"),
+        None => eprintln!("  Opcode:      unreadable"),
+    }
+    match error.instruction {
+        Some(instruction) => eprintln!("  Instruction: {instruction}"),
+        None => eprintln!("  Instruction: undecodable"),
+    }
+    eprintln!("  I:           {:#05x}", error.i);
+    if error.stack.is_empty() {
+        eprintln!("  Call stack:  empty");
+    } else {
+        eprintln!("  Call stack, most recent call first:");
+        for (depth, return_address) in error.stack.iter().rev().enumerate() {
+            eprintln!("    #{depth} returns to {:#05x}", return_address.0);
+        }
+    }
+}
+
+fn print_display(display: &mut Chip8Display) {
+    if display.has_changed() {
+        println!("{}", display);
+        display.render();
+    }
+}
+
+fn parse_stack_limit(arg: &str) -> Result<StackLimit, String> {
+    if arg == "unlimited" {
+        return Ok(StackLimit::Unlimited);
+    }
+    match arg.parse() {
+        Ok(0) => Err(String::from("stack limit must be at least 1")),
+        Ok(depth) => Ok(StackLimit::Depth(depth)),
+        Err(error) => Err(format!("{error}")),
+    }
+}
+
+fn parse_speed(arg: &str) -> Result<f64, String> {
+    let speed: f64 = arg.parse().map_err(|error| format!("{error}"))?;
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(String::from("speed must be a positive number"))
+    }
+}
+
+fn read_program(path: &Path) -> Vec<u8> {
+    let mut rom = File::open(path).unwrap();
     let mut program = vec![];
     rom.read_to_end(&mut program).unwrap();
+    program
+}
 
-    let mut executor = Executor::new(args.legacy_shift);
-    executor.load_program(&program).unwrap();
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let mut args = Chip8RunArgs::parse();
+    match args.command.take() {
+        Some(Command::Disasm(disasm_args)) => disassemble(&disasm_args),
+        Some(Command::Analyze(analyze_args)) => analyze(&analyze_args),
+        Some(Command::Asm(asm_args)) => assemble_source(*asm_args),
+        None => {
+            let program_path = args
+                .program_path
+                .expect("a program path is required without a subcommand");
+            run(&program_path, args.run)
+        }
+    }
+}
+
+fn disassemble(args: &DisasmArgs) -> ExitCode {
+    let program = read_program(&args.program_path);
+    let syntax = args.syntax.into();
+    for line in Disassembler::new(&program, PROGRAM_START, args.platform.into()) {
+        let opcode: String = line
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect();
+        println!(
+            "{:#05x}  {opcode:<8}  {}",
+            line.address.0,
+            line.assembly(syntax)
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn analyze(args: &DisasmArgs) -> ExitCode {
+    let program = read_program(&args.program_path);
+    let analysis = Analysis::new(&program, PROGRAM_START, args.platform.into());
+    print!("{}", analysis.listing(args.syntax.into()));
+    ExitCode::SUCCESS
+}
+
+fn assemble_source(args: AsmArgs) -> ExitCode {
+    let source = std::fs::read_to_string(&args.source_path).unwrap();
+    let assembled = match assemble(&source) {
+        Ok(assembled) => assembled,
+        Err(error) => {
+            eprintln!("{}: {error}", args.source_path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = args
+        .output
+        .unwrap_or_else(|| args.source_path.with_extension("ch8"));
+    std::fs::write(&output, &assembled.rom).unwrap();
+    if let Some(path) = args.symbols {
+        let symbols: String = assembled
+            .symbols
+            .iter()
+            .map(|(name, address)| format!("{:#05x} {name}\n", address.0))
+            .collect();
+        std::fs::write(path, symbols).unwrap();
+    }
+    if args.run {
+        run(&output, args.run_args)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn run(program_path: &Path, args: RunArgs) -> ExitCode {
+    if args.beep_frequency > max_beep_frequency(args.sample_rate) {
+        eprintln!(
+            "Beep frequency {} Hz must be below half the {} Hz sample rate",
+            args.beep_frequency, args.sample_rate
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let program = read_program(program_path);
+
+    let platform = args.platform.into();
+    let quirks = args.quirks.quirks(platform);
+    let rng = args
+        .seed
+        .map_or_else(XorShiftRng::from_entropy, XorShiftRng::from_seed);
+    let rpl_storage = args.rpl_path.map_or_else(
+        || FileRplStorage::for_rom(program_path),
+        FileRplStorage::new,
+    );
+    let mut executor = Executor::from_parts(quirks, rng, rpl_storage);
+    executor.set_platform(platform);
+    executor.set_font(args.font.into(), Address(args.font_address));
+    executor.set_stack_address(args.stack_address.map(Address));
+    executor.set_beeper(Beeper::new(args.beep_frequency, args.beep_volume));
+    if let Err(error) = executor.load_program(&program) {
+        eprintln!("{}: {error}", program_path.display());
+        return ExitCode::FAILURE;
+    }
 
-    let cycle_time = Duration::from_secs(1) / args.opcodes_per_second;
+    let mut wav_writer = args
+        .wav_output
+        .map(|path| WavWriter::new(File::create(path).unwrap(), args.sample_rate).unwrap());
 
-    loop {
-        let start = Instant::now();
-        if let Err(error) = executor.execute_once() {
-            error!("{error}");
+    let timing_model = TimingModel::from(args.timing);
+    if timing_model == TimingModel::CosmacVip && args.opcodes_per_second.is_some() {
+        eprintln!("Ignoring --opcodes-per-second, VIP timing runs at the VIP's own speed");
+    }
+    executor.set_timing_model(timing_model);
+    let mut budget = CycleBudget::new(
+        timing_model.cycles_per_second(
+            args.opcodes_per_second
+                .unwrap_or(DEFAULT_OPCODES_PER_SECOND),
+            FRAMES_PER_SECOND,
+        ),
+        FRAMES_PER_SECOND,
+    );
+    // Headless runs aren't tied to real time, running frames back to back as fast as possible
+    let clock: Box<dyn Clock> = if args.headless {
+        Box::new(VirtualClock::new())
+    } else {
+        Box::new(ScaledClock::new(WallClock::new(), args.speed))
+    };
+    let mut pacer = FramePacer::new(FRAMES_PER_SECOND, clock);
+    let mut cycles = 0;
+    let mut frames = 0;
+
+    let outcome = 'run: loop {
+        for _ in 0..pacer.wait_for_frames() {
+            match executor.run_state() {
+                RunState::Halted => break 'run RunOutcome::Exited,
+                RunState::Faulted(error) => {
+                    print_crash_report(error);
+                    break 'run RunOutcome::Faulted;
+                }
+                RunState::WaitingForKey { .. } if args.headless => {
+                    break 'run RunOutcome::WaitingForKey;
+                }
+                _ => {}
+            }
+            // Interactive runs keep the final screen up, and timers still running may yet be
+            // heard or waited on
+            if args.headless && executor.is_in_self_loop() && executor.are_timers_idle() {
+                break 'run RunOutcome::Exited;
+            }
+            if args
+                .max_frames
+                .is_some_and(|max_frames| frames >= max_frames)
+            {
+                break 'run RunOutcome::LimitReached;
+            }
+            let mut frame_cycles = budget.next_frame();
+            if let Some(max_cycles) = args.max_cycles {
+                if cycles >= max_cycles {
+                    break 'run RunOutcome::LimitReached;
+                }
+                let cycles_left = u32::try_from(max_cycles - cycles).unwrap_or(u32::MAX);
+                frame_cycles = frame_cycles.min(cycles_left);
+            }
+            // Errors fault the executor, and are reported from its run state
+            if let Ok(frame) = executor.run_frame(frame_cycles) {
+                cycles += u64::from(frame.cycles);
+            }
+            if let Some(wav_writer) = &mut wav_writer {
+                wav_writer
+                    .write_samples(&executor.audio_frame(args.sample_rate))
+                    .unwrap();
+            }
+            frames += 1;
+        }
+        if !args.headless {
+            print_display(executor.get_display_mut());
         }
-        let display = executor.get_display_mut();
-        if display.has_changed() {
-            println!("{}", display);
-            display.render();
+    };
+    if !args.headless {
+        print_display(executor.get_display_mut());
+    }
+
+    if let Some(wav_writer) = wav_writer {
+        wav_writer.finish().unwrap();
+    }
+    if let Some(path) = args.dump_screen {
+        let screen = executor.get_display_mut().to_string();
+        if path.as_os_str() == "-" {
+            print!("{screen}");
+        } else {
+            File::create(path)
+                .and_then(|mut file| file.write_all(screen.as_bytes()))
+                .unwrap();
         }
-        let run_elapsed = start.elapsed();
-        std::thread::sleep(cycle_time - run_elapsed);
     }
+    outcome.exit_code()
 }
@@ -0,0 +1,278 @@
+/// Bytes in an XO-CHIP audio pattern, one bit per sample
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+
+/// The pitch XO-CHIP programs start with, playing the pattern at 4000 bits per second
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// Amplitude of generated samples, leaving headroom below full scale
+pub const DEFAULT_AMPLITUDE: i16 = i16::MAX / 4;
+
+/// Frequency of the classic Chip8 beeper's tone, in Hz
+pub const DEFAULT_BEEP_FREQUENCY: u32 = 440;
+
+/// Volume of the classic Chip8 beeper, out of 255
+pub const DEFAULT_BEEP_VOLUME: u8 = 64;
+
+/// Rate the delay and sound timers count down at, and so the rate frames are produced at
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+const PATTERN_BITS: u64 = (AUDIO_PATTERN_SIZE * 8) as u64;
+const FRACTION_BITS: u32 = 32;
+
+/// XO-CHIP's audio: a 128 sample, 1 bit waveform looped at a rate set by the pitch register
+///
+/// Samples are generated on demand, so a frontend or file writer can pull as many as it
+/// needs at whatever sample rate it runs at.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub struct XoChipAudio {
+    pattern: [u8; AUDIO_PATTERN_SIZE],
+    pitch: u8,
+    /// Playback position within the pattern in bits, as 32.32 fixed point
+    position: u64,
+}
+
+impl XoChipAudio {
+    #[must_use]
+    pub fn new() -> Self {
+        XoChipAudio::default()
+    }
+
+    #[must_use]
+    pub fn pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE] {
+        &self.pattern
+    }
+
+    pub fn set_pattern(&mut self, pattern: [u8; AUDIO_PATTERN_SIZE]) {
+        self.pattern = pattern;
+    }
+
+    #[must_use]
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+    }
+
+    /// Gets the rate the pattern is played at, in bits per second
+    /// ```
+    /// # use eoxchip8::core::audio::XoChipAudio;
+    /// let mut audio = XoChipAudio::new();
+    /// assert_eq!(audio.playback_rate(), 4000.0);
+    /// audio.set_pitch(64 + 48);
+    /// assert_eq!(audio.playback_rate(), 8000.0);
+    /// ```
+    #[must_use]
+    pub fn playback_rate(&self) -> f64 {
+        4000.0 * 2f64.powf((f64::from(self.pitch) - 64.0) / 48.0)
+    }
+
+    /// Fills `samples` with signed 16 bit PCM at `sample_rate`
+    ///
+    /// While `playing` is false the samples are silent and the pattern restarts from its
+    /// beginning, so identical runs always produce identical samples.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn generate(&mut self, playing: bool, sample_rate: u32, samples: &mut [i16]) {
+        if !playing || sample_rate == 0 {
+            self.position = 0;
+            samples.fill(0);
+            return;
+        }
+
+        let step = (self.playback_rate() * (1u64 << FRACTION_BITS) as f64 / f64::from(sample_rate))
+            .round() as u64;
+        for sample in samples {
+            let bit = (self.position >> FRACTION_BITS) % PATTERN_BITS;
+            let byte = self.pattern[(bit / 8) as usize];
+            *sample = if byte & (0x80 >> (bit % 8)) != 0 {
+                DEFAULT_AMPLITUDE
+            } else {
+                -DEFAULT_AMPLITUDE
+            };
+            self.position = (self.position + step) % (PATTERN_BITS << FRACTION_BITS);
+        }
+    }
+}
+
+/// The classic Chip8 beeper, a square wave sounding while the sound timer is active
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub struct Beeper {
+    frequency: u32,
+    volume: u8,
+    /// Position within the current cycle of the wave, as 0.32 fixed point
+    phase: u32,
+}
+
+impl Beeper {
+    /// Creates a beeper sounding at `frequency` Hz, with a `volume` out of 255
+    #[must_use]
+    pub fn new(frequency: u32, volume: u8) -> Self {
+        Beeper {
+            frequency,
+            volume,
+            phase: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    pub fn set_frequency(&mut self, frequency: u32) {
+        self.frequency = frequency;
+    }
+
+    #[must_use]
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume;
+    }
+
+    /// Fills `samples` with signed 16 bit PCM at `sample_rate`
+    ///
+    /// While `playing` is false the samples are silent and the wave restarts at the start of
+    /// a cycle, so identical runs always produce identical samples. Frequencies at or above
+    /// the Nyquist limit are clamped to `max_beep_frequency`, as they can't be represented.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn generate(&mut self, playing: bool, sample_rate: u32, samples: &mut [i16]) {
+        if !playing || sample_rate == 0 {
+            self.phase = 0;
+            samples.fill(0);
+            return;
+        }
+
+        let amplitude = (i32::from(i16::MAX) * i32::from(self.volume) / 255) as i16;
+        let frequency = self.frequency.min(max_beep_frequency(sample_rate));
+        // Below half the sample rate, the step is under 1 << 31 and can't truncate
+        let step = ((u64::from(frequency) << 32) / u64::from(sample_rate)) as u32;
+        for sample in samples {
+            *sample = if self.phase < 1 << 31 {
+                amplitude
+            } else {
+                -amplitude
+            };
+            self.phase = self.phase.wrapping_add(step);
+        }
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Beeper::new(DEFAULT_BEEP_FREQUENCY, DEFAULT_BEEP_VOLUME)
+    }
+}
+
+/// Gets the highest beeper frequency below the Nyquist limit of `sample_rate`
+/// ```
+/// # use eoxchip8::core::audio::max_beep_frequency;
+/// assert_eq!(max_beep_frequency(44100), 22049);
+/// assert_eq!(max_beep_frequency(8001), 4000);
+/// ```
+#[must_use]
+pub fn max_beep_frequency(sample_rate: u32) -> u32 {
+    sample_rate.saturating_sub(1) / 2
+}
+
+/// Gets the number of samples in frame `frame` at `sample_rate`
+///
+/// Sample rates that don't divide evenly into frames have the remainder spread across
+/// frames, so every second of frames holds exactly `sample_rate` samples.
+/// ```
+/// # use eoxchip8::core::audio::samples_in_frame;
+/// let lengths: Vec<_> = (0..60).map(|frame| samples_in_frame(22050, frame)).collect();
+/// assert_eq!(lengths.iter().sum::<usize>(), 22050);
+/// assert!(lengths.iter().all(|len| *len == 367 || *len == 368));
+/// ```
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn samples_in_frame(sample_rate: u32, frame: u64) -> usize {
+    let rate = u128::from(sample_rate);
+    let frames_per_second = u128::from(FRAMES_PER_SECOND);
+    let frame = u128::from(frame);
+    let start = frame * rate / frames_per_second;
+    let end = (frame + 1) * rate / frames_per_second;
+    (end - start) as usize
+}
+
+impl Default for XoChipAudio {
+    fn default() -> Self {
+        XoChipAudio {
+            pattern: [0; AUDIO_PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            position: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silent_when_not_playing() {
+        let mut audio = XoChipAudio::new();
+        audio.set_pattern([0xFF; AUDIO_PATTERN_SIZE]);
+        let mut samples = [1; 8];
+        audio.generate(false, 8000, &mut samples);
+        assert_eq!(samples, [0; 8]);
+    }
+
+    #[test]
+    fn test_pattern_played_at_pitch() {
+        let mut audio = XoChipAudio::new();
+        let mut pattern = [0; AUDIO_PATTERN_SIZE];
+        pattern[0] = 0b1010_0000;
+        audio.set_pattern(pattern);
+        // At the default pitch, an 8000 Hz output plays each pattern bit twice
+        let mut samples = [0; 6];
+        audio.generate(true, 8000, &mut samples);
+        let (high, low) = (DEFAULT_AMPLITUDE, -DEFAULT_AMPLITUDE);
+        assert_eq!(samples, [high, high, low, low, high, high]);
+    }
+
+    #[test]
+    fn test_beeper_square_wave() {
+        let mut beeper = Beeper::new(1000, 255);
+        let mut samples = [0; 8];
+        beeper.generate(true, 4000, &mut samples);
+        let (high, low) = (i16::MAX, -i16::MAX);
+        assert_eq!(samples, [high, high, low, low, high, high, low, low]);
+        beeper.generate(false, 4000, &mut samples);
+        assert_eq!(samples, [0; 8]);
+    }
+
+    #[test]
+    fn test_beeper_clamped_below_nyquist() {
+        let mut clamped = [0; 8];
+        let mut expected = [0; 8];
+        Beeper::new(1999, 255).generate(true, 4000, &mut expected);
+        for frequency in [2000, 4000, u32::MAX] {
+            Beeper::new(frequency, 255).generate(true, 4000, &mut clamped);
+            assert_eq!(clamped, expected);
+        }
+        // Unclamped, a beep at the sample rate would step a whole cycle and stay high
+        assert!(expected.contains(&-i16::MAX));
+    }
+
+    #[test]
+    fn test_pattern_loops() {
+        let mut audio = XoChipAudio::new();
+        let mut pattern = [0; AUDIO_PATTERN_SIZE];
+        pattern[0] = 0x80;
+        audio.set_pattern(pattern);
+        let mut samples = [0; 129];
+        audio.generate(true, 4000, &mut samples);
+        assert_eq!(samples[0], DEFAULT_AMPLITUDE);
+        assert_eq!(samples[1], -DEFAULT_AMPLITUDE);
+        assert_eq!(samples[128], DEFAULT_AMPLITUDE);
+    }
+}
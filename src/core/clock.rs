@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+/// A source of time for pacing execution
+///
+/// Times are measured from when the clock was created, so clocks not tied to real time can
+/// start anywhere and be moved freely.
+pub trait Clock {
+    /// Time passed since the clock started
+    fn now(&self) -> Duration;
+
+    /// Blocks until the clock reaches `deadline`, returning immediately if it already has
+    fn sleep_until(&mut self, deadline: Duration);
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+
+    fn sleep_until(&mut self, deadline: Duration) {
+        (**self).sleep_until(deadline);
+    }
+}
+
+/// Real time, sleeping the current thread to wait
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub struct WallClock {
+    start: Instant,
+}
+
+impl WallClock {
+    #[must_use]
+    pub fn new() -> Self {
+        WallClock::default()
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        WallClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for WallClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep_until(&mut self, deadline: Duration) {
+        std::thread::sleep(deadline.saturating_sub(self.now()));
+    }
+}
+
+/// Time that only moves when told to, jumping straight to any deadline it's asked to wait for
+///
+/// Runs paced by a virtual clock go as fast as the host allows, and always see the same
+/// sequence of times, which keeps tests fast and deterministic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub struct VirtualClock {
+    now: Duration,
+}
+
+impl VirtualClock {
+    #[must_use]
+    pub fn new() -> Self {
+        VirtualClock::default()
+    }
+
+    /// Moves the clock forward by `duration`
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn sleep_until(&mut self, deadline: Duration) {
+        self.now = self.now.max(deadline);
+    }
+}
+
+/// Another clock sped up or slowed down by a fixed factor
+///
+/// A speed of 2 runs twice as fast as the inner clock, for fast-forwarding, while a speed
+/// of 0.5 runs at half speed, for slow motion.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct ScaledClock<C> {
+    inner: C,
+    speed: f64,
+}
+
+impl<C: Clock> ScaledClock<C> {
+    /// # Panics
+    ///
+    /// Panics if `speed` isn't a positive, finite number
+    #[must_use]
+    pub fn new(inner: C, speed: f64) -> Self {
+        assert!(
+            speed.is_finite() && speed > 0.0,
+            "clock speed must be positive and finite"
+        );
+        ScaledClock { inner, speed }
+    }
+
+    #[must_use]
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    #[must_use]
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: Clock> Clock for ScaledClock<C> {
+    fn now(&self) -> Duration {
+        self.inner.now().mul_f64(self.speed)
+    }
+
+    fn sleep_until(&mut self, deadline: Duration) {
+        self.inner.sleep_until(deadline.div_f64(self.speed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock_jumps_to_deadline() {
+        let mut clock = VirtualClock::new();
+        clock.sleep_until(Duration::from_millis(20));
+        assert_eq!(clock.now(), Duration::from_millis(20));
+        // Never moves backwards
+        clock.sleep_until(Duration::from_millis(10));
+        assert_eq!(clock.now(), Duration::from_millis(20));
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(clock.now(), Duration::from_millis(25));
+    }
+
+    #[test]
+    fn test_scaled_clock() {
+        let mut clock = ScaledClock::new(VirtualClock::new(), 2.0);
+        clock.inner_mut().advance(Duration::from_millis(10));
+        assert_eq!(clock.now(), Duration::from_millis(20));
+        clock.sleep_until(Duration::from_millis(60));
+        assert_eq!(clock.inner().now(), Duration::from_millis(30));
+        assert_eq!(clock.now(), Duration::from_millis(60));
+    }
+}
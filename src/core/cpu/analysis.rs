@@ -0,0 +1,464 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use crate::core::{memory::Address, platform::Platform};
+
+use super::{
+    disassembler::{DisassembledInstruction, Disassembler, Syntax},
+    instructions::{Instruction, LONG_LOAD_I_PREFIX},
+};
+
+/// Bytes of data listed on each line
+const DATA_BYTES_PER_LINE: usize = 8;
+
+/// How a program refers to an address, from the weakest evidence to the strongest
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub enum Reference {
+    /// Loaded into I
+    Data,
+    /// Loaded into I and then drawn
+    Sprite,
+    /// Jumped to
+    Jump,
+    /// Called
+    Subroutine,
+}
+
+impl Reference {
+    /// The name of the label for an address referred to this way
+    /// ```
+    /// # use eoxchip8::core::{cpu::analysis::Reference, memory::Address};
+    /// assert_eq!(Reference::Subroutine.label(Address(0x2A4)), "sub_2A4");
+    /// ```
+    #[must_use]
+    pub fn label(self, address: Address) -> String {
+        let prefix = match self {
+            Reference::Data => "data",
+            Reference::Sprite => "sprite",
+            Reference::Jump => "label",
+            Reference::Subroutine => "sub",
+        };
+        format!("{prefix}_{:03X}", address.0)
+    }
+}
+
+/// A run of instructions only entered at the start and only left at the end
+///
+/// Calls return to the instruction after them, so don't end a block.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct BasicBlock {
+    pub start: Address,
+    /// The address just past the last instruction
+    pub end: Address,
+    /// Where execution can continue after the block
+    pub successors: Vec<Address>,
+}
+
+/// A program split into code and data by following its control flow from the start
+///
+/// Starting at the origin, jumps, calls, skips and returns are followed to find every
+/// reachable instruction, and so every byte which is only data. Jumps through `BNNN` can go
+/// anywhere, so only their base address is followed. Addresses loaded into I are marked as
+/// data, or as sprites when drawn.
+/// ```
+/// # use eoxchip8::core::{cpu::analysis::Analysis, memory::Address, platform::Platform};
+/// let program = [0x12, 0x04, 0xFF, 0xFF, 0x12, 0x04];
+/// let analysis = Analysis::new(&program, Address(0x200), Platform::Chip8);
+/// assert!(analysis.is_code(Address(0x200)));
+/// assert!(!analysis.is_code(Address(0x202)));
+/// ```
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct Analysis<'a> {
+    program: &'a [u8],
+    origin: Address,
+    platform: Platform,
+    instructions: BTreeMap<Address, DisassembledInstruction>,
+    blocks: Vec<BasicBlock>,
+    references: BTreeMap<Address, Reference>,
+}
+
+impl<'a> Analysis<'a> {
+    /// Analyzes `program`, loaded at `origin` on `platform`
+    #[must_use]
+    pub fn new(program: &'a [u8], origin: Address, platform: Platform) -> Self {
+        let mut analysis = Analysis {
+            program,
+            origin,
+            platform,
+            instructions: BTreeMap::new(),
+            blocks: Vec::new(),
+            references: BTreeMap::new(),
+        };
+        let leaders = analysis.trace();
+        analysis.find_blocks(&leaders);
+        analysis
+    }
+
+    /// The reachable instructions, in address order
+    pub fn instructions(&self) -> impl Iterator<Item = &DisassembledInstruction> {
+        self.instructions.values()
+    }
+
+    /// The reachable instruction starting at `address`, if there is one
+    #[must_use]
+    pub fn instruction_at(&self, address: Address) -> Option<&DisassembledInstruction> {
+        self.instructions.get(&address)
+    }
+
+    /// Whether the byte at `address` is part of a reachable instruction
+    #[must_use]
+    pub fn is_code(&self, address: Address) -> bool {
+        self.instructions.contains_key(&address) || self.is_inside_instruction(address)
+    }
+
+    /// The basic blocks of the reachable code, in address order
+    #[must_use]
+    pub fn basic_blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// The addresses the program refers to, and how it refers to them
+    #[must_use]
+    pub fn references(&self) -> &BTreeMap<Address, Reference> {
+        &self.references
+    }
+
+    /// The label given to `address`, if it's referred to and can be labelled in the listing
+    ///
+    /// Addresses outside the program, or in the middle of an instruction, are left unlabelled.
+    #[must_use]
+    pub fn label(&self, address: Address) -> Option<String> {
+        let reference = self.references.get(&address)?;
+        let labelled = self.offset_of(address).is_some() && !self.is_inside_instruction(address);
+        labelled.then(|| reference.label(address))
+    }
+
+    /// Lists the program in `syntax`, with labels in place of referenced addresses
+    ///
+    /// Every byte of the program is listed in order, as an instruction when it's reachable
+    /// code and as data otherwise, so assembling the listing gives back the same program.
+    #[must_use]
+    pub fn listing(&self, syntax: Syntax) -> String {
+        let mut listing = String::new();
+        let mut data = Vec::new();
+        let mut offset = 0;
+        while let Some(&byte) = self.program.get(offset) {
+            let address = self.address_of(offset);
+            let line = self.instructions.get(&address);
+            let label = self.label(address);
+            if line.is_some() || label.is_some() || data.len() == DATA_BYTES_PER_LINE {
+                write_data(&mut listing, syntax, &mut data);
+            }
+            if let Some(label) = label {
+                writeln!(listing, "{}", syntax.label(&label)).unwrap();
+            }
+            if let Some(line) = line {
+                writeln!(listing, "    {}", self.assembly(line, syntax)).unwrap();
+                offset += line.bytes.len();
+            } else {
+                data.push(byte);
+                offset += 1;
+            }
+        }
+        write_data(&mut listing, syntax, &mut data);
+        listing
+    }
+
+    /// Follows the control flow from the origin, returning the addresses starting blocks
+    fn trace(&mut self) -> BTreeSet<Address> {
+        let mut leaders = BTreeSet::from([self.origin]);
+        let mut pending = vec![self.origin];
+        while let Some(address) = pending.pop() {
+            if self.instructions.contains_key(&address) {
+                continue;
+            }
+            let Some(line) = self.decode(address) else {
+                continue;
+            };
+            let Some(instruction) = line.instruction else {
+                continue;
+            };
+            let (successors, ends_block) = self.flow(address, instruction);
+            match instruction {
+                Instruction::JumpTo { address } | Instruction::JumpWithOffset { address } => {
+                    self.refer(address, Reference::Jump);
+                }
+                Instruction::Call { address } => {
+                    self.refer(address, Reference::Subroutine);
+                    leaders.insert(address);
+                }
+                Instruction::LoadIImm { imm } | Instruction::LoadILong { imm } => {
+                    self.refer(Address(imm), Reference::Data);
+                }
+                _ => {}
+            }
+            if ends_block {
+                leaders.extend(&successors);
+            }
+            pending.extend(successors);
+            self.instructions.insert(address, line);
+        }
+        leaders
+    }
+
+    /// Splits the reachable instructions into blocks, and marks the data they draw as sprites
+    fn find_blocks(&mut self, leaders: &BTreeSet<Address>) {
+        for &start in leaders {
+            let mut address = start;
+            let mut i = None;
+            while let Some(&DisassembledInstruction {
+                instruction: Some(instruction),
+                ..
+            }) = self.instructions.get(&address)
+            {
+                match instruction {
+                    Instruction::LoadIImm { imm } | Instruction::LoadILong { imm } => {
+                        i = Some(Address(imm));
+                    }
+                    Instruction::Draw { .. } => {
+                        if let Some(sprite) = i {
+                            self.refer(sprite, Reference::Sprite);
+                        }
+                    }
+                    Instruction::AddIV { .. }
+                    | Instruction::LoadISprite { .. }
+                    | Instruction::LoadIBigSprite { .. }
+                    | Instruction::SaveRegistersToMem { .. }
+                    | Instruction::LoadRegistersFromMem { .. }
+                    | Instruction::BCDRegister { .. } => i = None,
+                    _ => {}
+                }
+                let (successors, ends_block) = self.flow(address, instruction);
+                let next = self.next_address(address);
+                if ends_block || leaders.contains(&next) || !self.instructions.contains_key(&next) {
+                    let successors = if ends_block { successors } else { vec![next] };
+                    self.blocks.push(BasicBlock {
+                        start,
+                        end: next,
+                        successors,
+                    });
+                    break;
+                }
+                address = next;
+            }
+        }
+    }
+
+    /// Where execution can go after the instruction at `address`, and whether it's a branch
+    fn flow(&self, address: Address, instruction: Instruction) -> (Vec<Address>, bool) {
+        let next = self.next_address(address);
+        match instruction {
+            Instruction::JumpTo { address } | Instruction::JumpWithOffset { address } => {
+                (vec![address], true)
+            }
+            Instruction::Call { address } => (vec![address, next], false),
+            Instruction::Return | Instruction::Exit => (Vec::new(), true),
+            Instruction::SkipIfEqVImm { .. }
+            | Instruction::SkipIfNotEqVImm { .. }
+            | Instruction::SkipIfEqualV2 { .. }
+            | Instruction::SkipIfNotEqualV2 { .. }
+            | Instruction::SkipIfKeyPressed { .. }
+            | Instruction::SkipIfKeyNotPressed { .. } => {
+                let skipped = if self.is_long(next) {
+                    next.offset(4)
+                } else {
+                    next.offset(2)
+                };
+                (vec![next, skipped], true)
+            }
+            _ => (vec![next], false),
+        }
+    }
+
+    /// Records a reference to `address`, keeping the strongest if it's referred to many ways
+    fn refer(&mut self, address: Address, reference: Reference) {
+        let strongest = self.references.entry(address).or_insert(reference);
+        *strongest = (*strongest).max(reference);
+    }
+
+    /// Decodes the instruction at `address`, as data if this platform can't execute it
+    fn decode(&self, address: Address) -> Option<DisassembledInstruction> {
+        let offset = self.offset_of(address)?;
+        let mut line = Disassembler::new(&self.program[offset..], address, self.platform).next()?;
+        if !line
+            .instruction
+            .is_some_and(|instruction| self.platform.supports(&instruction))
+        {
+            line.instruction = None;
+        }
+        Some(line)
+    }
+
+    /// Whether `address` is past the start of an instruction but still part of it
+    fn is_inside_instruction(&self, address: Address) -> bool {
+        self.instructions
+            .range(..address)
+            .next_back()
+            .is_some_and(|(start, line)| usize::from(address.0 - start.0) < line.bytes.len())
+    }
+
+    fn next_address(&self, address: Address) -> Address {
+        let size = self.instructions.get(&address).map_or_else(
+            || if self.is_long(address) { 4 } else { 2 },
+            |line| line.bytes.len(),
+        );
+        // Instructions are at most 4 bytes
+        #[allow(clippy::cast_possible_truncation)]
+        address.offset(size as u16)
+    }
+
+    fn is_long(&self, address: Address) -> bool {
+        self.platform.is_xo_chip() && self.word_at(address) == Some(LONG_LOAD_I_PREFIX)
+    }
+
+    fn word_at(&self, address: Address) -> Option<u16> {
+        let offset = self.offset_of(address)?;
+        match self.program[offset..] {
+            [high, low, ..] => Some(u16::from_be_bytes([high, low])),
+            _ => None,
+        }
+    }
+
+    /// The offset of `address` into the program, if it's inside it
+    fn offset_of(&self, address: Address) -> Option<usize> {
+        let offset = usize::from(address.0.checked_sub(self.origin.0)?);
+        (offset < self.program.len()).then_some(offset)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn address_of(&self, offset: usize) -> Address {
+        self.origin.offset(offset as u16)
+    }
+
+    /// Renders an instruction, naming the address it refers to by its label
+    fn assembly(&self, line: &DisassembledInstruction, syntax: Syntax) -> String {
+        let Some(instruction) = line.instruction else {
+            return syntax.data(&line.bytes);
+        };
+        let target = match instruction {
+            Instruction::JumpTo { address }
+            | Instruction::Call { address }
+            | Instruction::JumpWithOffset { address } => address,
+            Instruction::LoadIImm { imm } | Instruction::LoadILong { imm } => Address(imm),
+            _ => return instruction.assembly(syntax).to_string(),
+        };
+        let Some(label) = self.label(target) else {
+            return instruction.assembly(syntax).to_string();
+        };
+        match (syntax, instruction) {
+            (Syntax::Cowgod, Instruction::JumpTo { .. }) => format!("JP {label}"),
+            (Syntax::Cowgod, Instruction::Call { .. }) => format!("CALL {label}"),
+            (Syntax::Cowgod, Instruction::JumpWithOffset { .. }) => format!("JP V0, {label}"),
+            (Syntax::Cowgod, _) => format!("LD I, {label}"),
+            (Syntax::Octo, Instruction::JumpTo { .. }) => format!("jump {label}"),
+            (Syntax::Octo, Instruction::Call { .. }) => format!(":call {label}"),
+            (Syntax::Octo, Instruction::JumpWithOffset { .. }) => format!("jump0 {label}"),
+            (Syntax::Octo, Instruction::LoadILong { .. }) => format!("i := long {label}"),
+            (Syntax::Octo, _) => format!("i := {label}"),
+        }
+    }
+}
+
+/// Lists the data collected so far on its own line, if there is any
+fn write_data(listing: &mut String, syntax: Syntax, data: &mut Vec<u8>) {
+    if !data.is_empty() {
+        writeln!(listing, "    {}", syntax.data(data)).unwrap();
+        data.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draws a sprite kept between its code and a subroutine, then loops forever
+    const PROGRAM: [u8; 24] = [
+        0xA2, 0x0A, // i := sprite
+        0x60, 0x00, // v0 := 0
+        0xD0, 0x05, // sprite v0 v0 5
+        0x22, 0x10, // :call sub
+        0x12, 0x08, // jump to itself
+        0xF0, 0x90, 0x90, 0x90, 0xF0, 0x00, // the sprite, padded
+        0x30, 0x01, // if v0 != 1 then
+        0x70, 0x01, // v0 += 1
+        0x00, 0xEE, // return
+        0xFF, 0xFF, // unreachable
+    ];
+
+    #[test]
+    fn test_separates_code_from_data() {
+        let analysis = Analysis::new(&PROGRAM, Address(0x200), Platform::Chip8);
+        assert_eq!(analysis.instructions().count(), 8);
+        assert!(analysis.is_code(Address(0x209)));
+        assert!(!analysis.is_code(Address(0x20A)));
+        assert!(!analysis.is_code(Address(0x216)));
+        assert_eq!(
+            analysis.references().iter().collect::<Vec<_>>(),
+            [
+                (&Address(0x208), &Reference::Jump),
+                (&Address(0x20A), &Reference::Sprite),
+                (&Address(0x210), &Reference::Subroutine),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_basic_blocks() {
+        let analysis = Analysis::new(&PROGRAM, Address(0x200), Platform::Chip8);
+        let blocks: Vec<(u16, u16, Vec<Address>)> = analysis
+            .basic_blocks()
+            .iter()
+            .map(|block| (block.start.0, block.end.0, block.successors.clone()))
+            .collect();
+        assert_eq!(
+            blocks,
+            [
+                (0x200, 0x208, vec![Address(0x208)]),
+                (0x208, 0x20A, vec![Address(0x208)]),
+                (0x210, 0x212, vec![Address(0x212), Address(0x214)]),
+                (0x212, 0x214, vec![Address(0x214)]),
+                (0x214, 0x216, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_listing() {
+        let analysis = Analysis::new(&PROGRAM, Address(0x200), Platform::Chip8);
+        assert_eq!(
+            analysis.listing(Syntax::Octo),
+            "    i := sprite_20A
+    v0 := 0
+    sprite v0 v0 5
+    :call sub_210
+: label_208
+    jump label_208
+: sprite_20A
+    0xF0 0x90 0x90 0x90 0xF0 0x00
+: sub_210
+    if v0 != 1 then
+    v0 += 1
+    return
+    0xFF 0xFF
+"
+        );
+    }
+
+    #[test]
+    fn test_skips_over_long_instructions() {
+        let program = [0x30, 0x00, 0xF0, 0x00, 0x02, 0x08, 0x00, 0xFD];
+        let analysis = Analysis::new(&program, Address(0x200), Platform::XoChip);
+        assert_eq!(
+            analysis.basic_blocks()[0].successors,
+            [Address(0x202), Address(0x206)]
+        );
+        assert_eq!(
+            analysis.references().get(&Address(0x208)),
+            Some(&Reference::Data)
+        );
+        // Past the end of the program, so left as an address
+        assert_eq!(analysis.label(Address(0x208)), None);
+    }
+}
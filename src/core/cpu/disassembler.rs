@@ -0,0 +1,439 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::core::{memory::Address, platform::Platform};
+
+use super::instructions::{Instruction, LONG_LOAD_I_PREFIX};
+
+/// Assembly languages instructions can be rendered in
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub enum Syntax {
+    /// The mnemonics of Cowgod's Chip-8 technical reference, such as `ADD V3, 0x01`
+    #[default]
+    Cowgod,
+    /// The language of the Octo assembler, such as `v3 += 1`
+    Octo,
+}
+
+impl Syntax {
+    /// Renders bytes which aren't an instruction as data
+    /// ```
+    /// # use eoxchip8::core::cpu::disassembler::Syntax;
+    /// assert_eq!(Syntax::Cowgod.data(&[0xE0, 0x00]), "DB 0xE0, 0x00");
+    /// assert_eq!(Syntax::Octo.data(&[0xE0, 0x00]), "0xE0 0x00");
+    /// ```
+    #[must_use]
+    pub fn data(self, bytes: &[u8]) -> String {
+        let bytes: Vec<String> = bytes.iter().map(|byte| format!("0x{byte:02X}")).collect();
+        match self {
+            Syntax::Cowgod => format!("DB {}", bytes.join(", ")),
+            Syntax::Octo => bytes.join(" "),
+        }
+    }
+
+    /// Renders the definition of a label
+    /// ```
+    /// # use eoxchip8::core::cpu::disassembler::Syntax;
+    /// assert_eq!(Syntax::Cowgod.label("loop"), "loop:");
+    /// assert_eq!(Syntax::Octo.label("loop"), ": loop");
+    /// ```
+    #[must_use]
+    pub fn label(self, name: &str) -> String {
+        match self {
+            Syntax::Cowgod => format!("{name}:"),
+            Syntax::Octo => format!(": {name}"),
+        }
+    }
+}
+
+/// An instruction rendered in an assembly syntax, see [`Instruction::assembly`]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub struct Assembly<'a> {
+    instruction: &'a Instruction,
+    syntax: Syntax,
+}
+
+impl Instruction {
+    /// Renders this instruction in `syntax`
+    /// ```
+    /// # use eoxchip8::core::cpu::{disassembler::Syntax, instructions::Instruction};
+    /// let instruction = Instruction::AddVImm { reg_num: 3, imm: 1 };
+    /// assert_eq!(instruction.assembly(Syntax::Cowgod).to_string(), "ADD V3, 0x01");
+    /// assert_eq!(instruction.assembly(Syntax::Octo).to_string(), "v3 += 1");
+    /// ```
+    #[must_use]
+    pub fn assembly(&self, syntax: Syntax) -> Assembly<'_> {
+        Assembly {
+            instruction: self,
+            syntax,
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.assembly(Syntax::Cowgod).fmt(f)
+    }
+}
+
+impl Display for Assembly<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.syntax {
+            Syntax::Cowgod => write_cowgod(*self.instruction, f),
+            Syntax::Octo => write_octo(*self.instruction, f),
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn write_cowgod(instruction: Instruction, f: &mut Formatter<'_>) -> fmt::Result {
+    match instruction {
+        Instruction::ClearScreen => write!(f, "CLS"),
+        Instruction::Return => write!(f, "RET"),
+        Instruction::Sys { address } => write!(f, "SYS 0x{:03X}", address.0),
+        Instruction::JumpTo { address } => write!(f, "JP 0x{:03X}", address.0),
+        Instruction::Call { address } => write!(f, "CALL 0x{:03X}", address.0),
+        Instruction::SkipIfEqVImm { reg_num, imm } => write!(f, "SE V{reg_num:X}, 0x{imm:02X}"),
+        Instruction::SkipIfNotEqVImm { reg_num, imm } => {
+            write!(f, "SNE V{reg_num:X}, 0x{imm:02X}")
+        }
+        Instruction::SkipIfEqualV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "SE V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::LoadVImm { reg_num, imm } => write!(f, "LD V{reg_num:X}, 0x{imm:02X}"),
+        Instruction::AddVImm { reg_num, imm } => write!(f, "ADD V{reg_num:X}, 0x{imm:02X}"),
+        Instruction::SetEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "LD V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::BitWiseOrEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "OR V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::BitWiseAndEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "AND V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::BitWiseXorEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "XOR V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::AddV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "ADD V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::SubV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "SUB V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::ShiftRight {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "SHR V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::SubNV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "SUBN V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::ShiftLeft {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "SHL V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::SkipIfNotEqualV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "SNE V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::LoadIImm { imm } => write!(f, "LD I, 0x{imm:03X}"),
+        Instruction::JumpWithOffset { address } => write!(f, "JP V0, 0x{:03X}", address.0),
+        Instruction::LoadVRandom { reg_num, imm } => write!(f, "RND V{reg_num:X}, 0x{imm:02X}"),
+        Instruction::Draw {
+            x_reg_num,
+            y_reg_num,
+            sprite_length,
+        } => write!(f, "DRW V{x_reg_num:X}, V{y_reg_num:X}, {sprite_length}"),
+        Instruction::SkipIfKeyPressed { register_num } => write!(f, "SKP V{register_num:X}"),
+        Instruction::SkipIfKeyNotPressed { register_num } => write!(f, "SKNP V{register_num:X}"),
+        Instruction::LoadVDelayTimer { register_num } => write!(f, "LD V{register_num:X}, DT"),
+        Instruction::WaitForKey { register_num } => write!(f, "LD V{register_num:X}, K"),
+        Instruction::SetDelayTimer { register_num } => write!(f, "LD DT, V{register_num:X}"),
+        Instruction::SetSoundTimer { register_num } => write!(f, "LD ST, V{register_num:X}"),
+        Instruction::AddIV { register_num } => write!(f, "ADD I, V{register_num:X}"),
+        Instruction::LoadISprite { register_num } => write!(f, "LD F, V{register_num:X}"),
+        Instruction::BCDRegister { register_num } => write!(f, "LD B, V{register_num:X}"),
+        Instruction::SaveRegistersToMem { max_reg_num } => write!(f, "LD [I], V{max_reg_num:X}"),
+        Instruction::LoadRegistersFromMem { max_reg_num } => {
+            write!(f, "LD V{max_reg_num:X}, [I]")
+        }
+        Instruction::ScrollDown { rows } => write!(f, "SCD {rows}"),
+        Instruction::ScrollUp { rows } => write!(f, "SCU {rows}"),
+        Instruction::ScrollRight => write!(f, "SCR"),
+        Instruction::ScrollLeft => write!(f, "SCL"),
+        Instruction::Exit => write!(f, "EXIT"),
+        Instruction::LowRes => write!(f, "LOW"),
+        Instruction::HighRes => write!(f, "HIGH"),
+        Instruction::LoadIBigSprite { register_num } => write!(f, "LD HF, V{register_num:X}"),
+        Instruction::SaveRegistersToFlags { max_reg_num } => write!(f, "LD R, V{max_reg_num:X}"),
+        Instruction::LoadRegistersFromFlags { max_reg_num } => {
+            write!(f, "LD V{max_reg_num:X}, R")
+        }
+        Instruction::SaveRegisterRange {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "SAVE V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::LoadRegisterRange {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "LOAD V{x_reg_num:X}, V{y_reg_num:X}"),
+        Instruction::SelectPlanes { planes } => write!(f, "PLANE {planes}"),
+        Instruction::LoadILong { imm } => write!(f, "LD I, 0x{imm:04X}"),
+        Instruction::LoadAudioPattern => write!(f, "AUDIO"),
+        Instruction::SetPitch { register_num } => write!(f, "PITCH V{register_num:X}"),
+    }
+}
+
+/// Octo has no syntax for the skips on their own, so they're written as the `if ... then` which
+/// assembles to them, skipping the next instruction when the condition is false
+#[allow(clippy::too_many_lines)]
+fn write_octo(instruction: Instruction, f: &mut Formatter<'_>) -> fmt::Result {
+    match instruction {
+        Instruction::ClearScreen => write!(f, "clear"),
+        Instruction::Return => write!(f, "return"),
+        Instruction::Sys { address } => {
+            let [high, low] = address.0.to_be_bytes();
+            write!(f, "0x{high:02X} 0x{low:02X}")
+        }
+        Instruction::JumpTo { address } => write!(f, "jump 0x{:03X}", address.0),
+        Instruction::Call { address } => write!(f, ":call 0x{:03X}", address.0),
+        Instruction::SkipIfEqVImm { reg_num, imm } => write!(f, "if v{reg_num:x} != {imm} then"),
+        Instruction::SkipIfNotEqVImm { reg_num, imm } => {
+            write!(f, "if v{reg_num:x} == {imm} then")
+        }
+        Instruction::SkipIfEqualV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "if v{x_reg_num:x} != v{y_reg_num:x} then"),
+        Instruction::LoadVImm { reg_num, imm } => write!(f, "v{reg_num:x} := {imm}"),
+        Instruction::AddVImm { reg_num, imm } => write!(f, "v{reg_num:x} += {imm}"),
+        Instruction::SetEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} := v{y_reg_num:x}"),
+        Instruction::BitWiseOrEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} |= v{y_reg_num:x}"),
+        Instruction::BitWiseAndEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} &= v{y_reg_num:x}"),
+        Instruction::BitWiseXorEqual {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} ^= v{y_reg_num:x}"),
+        Instruction::AddV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} += v{y_reg_num:x}"),
+        Instruction::SubV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} -= v{y_reg_num:x}"),
+        Instruction::ShiftRight {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} >>= v{y_reg_num:x}"),
+        Instruction::SubNV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} =- v{y_reg_num:x}"),
+        Instruction::ShiftLeft {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "v{x_reg_num:x} <<= v{y_reg_num:x}"),
+        Instruction::SkipIfNotEqualV2 {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "if v{x_reg_num:x} == v{y_reg_num:x} then"),
+        Instruction::LoadIImm { imm } => write!(f, "i := 0x{imm:03X}"),
+        Instruction::JumpWithOffset { address } => write!(f, "jump0 0x{:03X}", address.0),
+        Instruction::LoadVRandom { reg_num, imm } => {
+            write!(f, "v{reg_num:x} := random 0x{imm:02X}")
+        }
+        Instruction::Draw {
+            x_reg_num,
+            y_reg_num,
+            sprite_length,
+        } => write!(f, "sprite v{x_reg_num:x} v{y_reg_num:x} {sprite_length}"),
+        Instruction::SkipIfKeyPressed { register_num } => {
+            write!(f, "if v{register_num:x} -key then")
+        }
+        Instruction::SkipIfKeyNotPressed { register_num } => {
+            write!(f, "if v{register_num:x} key then")
+        }
+        Instruction::LoadVDelayTimer { register_num } => write!(f, "v{register_num:x} := delay"),
+        Instruction::WaitForKey { register_num } => write!(f, "v{register_num:x} := key"),
+        Instruction::SetDelayTimer { register_num } => write!(f, "delay := v{register_num:x}"),
+        Instruction::SetSoundTimer { register_num } => write!(f, "buzzer := v{register_num:x}"),
+        Instruction::AddIV { register_num } => write!(f, "i += v{register_num:x}"),
+        Instruction::LoadISprite { register_num } => write!(f, "i := hex v{register_num:x}"),
+        Instruction::BCDRegister { register_num } => write!(f, "bcd v{register_num:x}"),
+        Instruction::SaveRegistersToMem { max_reg_num } => write!(f, "save v{max_reg_num:x}"),
+        Instruction::LoadRegistersFromMem { max_reg_num } => write!(f, "load v{max_reg_num:x}"),
+        Instruction::ScrollDown { rows } => write!(f, "scroll-down {rows}"),
+        Instruction::ScrollUp { rows } => write!(f, "scroll-up {rows}"),
+        Instruction::ScrollRight => write!(f, "scroll-right"),
+        Instruction::ScrollLeft => write!(f, "scroll-left"),
+        Instruction::Exit => write!(f, "exit"),
+        Instruction::LowRes => write!(f, "lores"),
+        Instruction::HighRes => write!(f, "hires"),
+        Instruction::LoadIBigSprite { register_num } => {
+            write!(f, "i := bighex v{register_num:x}")
+        }
+        Instruction::SaveRegistersToFlags { max_reg_num } => {
+            write!(f, "saveflags v{max_reg_num:x}")
+        }
+        Instruction::LoadRegistersFromFlags { max_reg_num } => {
+            write!(f, "loadflags v{max_reg_num:x}")
+        }
+        Instruction::SaveRegisterRange {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "save v{x_reg_num:x} - v{y_reg_num:x}"),
+        Instruction::LoadRegisterRange {
+            x_reg_num,
+            y_reg_num,
+        } => write!(f, "load v{x_reg_num:x} - v{y_reg_num:x}"),
+        Instruction::SelectPlanes { planes } => write!(f, "plane {planes}"),
+        Instruction::LoadILong { imm } => write!(f, "i := long 0x{imm:04X}"),
+        Instruction::LoadAudioPattern => write!(f, "audio"),
+        Instruction::SetPitch { register_num } => write!(f, "pitch := v{register_num:x}"),
+    }
+}
+
+/// An instruction read from a program, or bytes which don't decode to one
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct DisassembledInstruction {
+    pub address: Address,
+    pub bytes: Vec<u8>,
+    pub instruction: Option<Instruction>,
+}
+
+impl DisassembledInstruction {
+    /// Renders the instruction in `syntax`, or its bytes as data if it didn't decode
+    #[must_use]
+    pub fn assembly(&self, syntax: Syntax) -> String {
+        match &self.instruction {
+            Some(instruction) => instruction.assembly(syntax).to_string(),
+            None => syntax.data(&self.bytes),
+        }
+    }
+}
+
+/// Reads a program as a sequence of instructions, from start to end
+///
+/// Every two bytes are treated as an instruction, including any data mixed in with the
+/// code, except for XO-CHIP's four byte F000 NNNN.
+/// ```
+/// # use eoxchip8::core::{cpu::disassembler::*, memory::Address, platform::Platform};
+/// let program = [0x60, 0x01, 0x12, 0x02];
+/// let lines: Vec<String> = Disassembler::new(&program, Address(0x200), Platform::Chip8)
+///     .map(|line| line.assembly(Syntax::Octo))
+///     .collect();
+/// assert_eq!(lines, ["v0 := 1", "jump 0x202"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct Disassembler<'a> {
+    program: &'a [u8],
+    origin: Address,
+    platform: Platform,
+    offset: usize,
+}
+
+impl<'a> Disassembler<'a> {
+    /// Creates a disassembler for `program`, loaded at `origin` on `platform`
+    #[must_use]
+    pub fn new(program: &'a [u8], origin: Address, platform: Platform) -> Self {
+        Disassembler {
+            program,
+            origin,
+            platform,
+            offset: 0,
+        }
+    }
+}
+
+impl Iterator for Disassembler<'_> {
+    type Item = DisassembledInstruction;
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self
+            .program
+            .get(self.offset..)
+            .filter(|rest| !rest.is_empty())?;
+        let address = self.origin.offset(self.offset as u16);
+        let (size, instruction) = match *rest {
+            [high, low, imm_high, imm_low, ..]
+                if u16::from_be_bytes([high, low]) == LONG_LOAD_I_PREFIX
+                    && self.platform.is_xo_chip() =>
+            {
+                let imm = u16::from_be_bytes([imm_high, imm_low]);
+                (4, Some(Instruction::LoadILong { imm }))
+            }
+            [high, low, ..] => (
+                2,
+                Instruction::try_from(u16::from_be_bytes([high, low])).ok(),
+            ),
+            _ => (rest.len(), None),
+        };
+        self.offset += size;
+        Some(DisassembledInstruction {
+            address,
+            bytes: rest[..size].to_vec(),
+            instruction,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assembly(opcode: u16, syntax: Syntax) -> String {
+        Instruction::try_from(opcode)
+            .unwrap()
+            .assembly(syntax)
+            .to_string()
+    }
+
+    #[test]
+    fn test_cowgod_syntax() {
+        assert_eq!(assembly(0x00E0, Syntax::Cowgod), "CLS");
+        assert_eq!(assembly(0x2ABC, Syntax::Cowgod), "CALL 0xABC");
+        assert_eq!(assembly(0x8AB4, Syntax::Cowgod), "ADD VA, VB");
+        assert_eq!(assembly(0xD125, Syntax::Cowgod), "DRW V1, V2, 5");
+        assert_eq!(assembly(0xF355, Syntax::Cowgod), "LD [I], V3");
+        assert_eq!(assembly(0x00C4, Syntax::Cowgod), "SCD 4");
+    }
+
+    #[test]
+    fn test_octo_syntax() {
+        assert_eq!(assembly(0x3F10, Syntax::Octo), "if vf != 16 then");
+        assert_eq!(assembly(0x8AB7, Syntax::Octo), "va =- vb");
+        assert_eq!(assembly(0xC10F, Syntax::Octo), "v1 := random 0x0F");
+        assert_eq!(assembly(0xE29E, Syntax::Octo), "if v2 -key then");
+        assert_eq!(assembly(0x5132, Syntax::Octo), "save v1 - v3");
+        assert_eq!(assembly(0x0123, Syntax::Octo), "0x01 0x23");
+    }
+
+    #[test]
+    fn test_disassembler_reads_long_instructions_and_data() {
+        let program = [0xF0, 0x00, 0x12, 0x34, 0xE0, 0x00, 0xFF];
+        let lines: Vec<DisassembledInstruction> =
+            Disassembler::new(&program, Address(0x200), Platform::XoChip).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].assembly(Syntax::Cowgod), "LD I, 0x1234");
+        assert_eq!(lines[1].address, Address(0x204));
+        assert_eq!(lines[1].assembly(Syntax::Cowgod), "DB 0xE0, 0x00");
+        assert_eq!(lines[2].bytes, [0xFF]);
+    }
+}
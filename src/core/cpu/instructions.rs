@@ -3,6 +3,9 @@ use thiserror::Error;
 
 use crate::core::memory::Address;
 
+/// The first word of XO-CHIP's four byte F000 NNNN, which loads I with the word after it
+pub const LONG_LOAD_I_PREFIX: u16 = 0xF000;
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq)]
 pub enum Instruction {
     ClearScreen,
@@ -96,6 +99,72 @@ pub enum Instruction {
     AddIV {
         register_num: u8,
     },
+    LoadVDelayTimer {
+        register_num: u8,
+    },
+    SetDelayTimer {
+        register_num: u8,
+    },
+    SetSoundTimer {
+        register_num: u8,
+    },
+    SkipIfKeyPressed {
+        register_num: u8,
+    },
+    SkipIfKeyNotPressed {
+        register_num: u8,
+    },
+    WaitForKey {
+        register_num: u8,
+    },
+    LoadVRandom {
+        reg_num: u8,
+        imm: u8,
+    },
+    LoadISprite {
+        register_num: u8,
+    },
+    JumpWithOffset {
+        address: Address,
+    },
+    ScrollDown {
+        rows: u8,
+    },
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    LowRes,
+    HighRes,
+    LoadIBigSprite {
+        register_num: u8,
+    },
+    ScrollUp {
+        rows: u8,
+    },
+    SaveRegisterRange {
+        x_reg_num: u8,
+        y_reg_num: u8,
+    },
+    LoadRegisterRange {
+        x_reg_num: u8,
+        y_reg_num: u8,
+    },
+    SelectPlanes {
+        planes: u8,
+    },
+    LoadILong {
+        imm: u16,
+    },
+    SaveRegistersToFlags {
+        max_reg_num: u8,
+    },
+    LoadRegistersFromFlags {
+        max_reg_num: u8,
+    },
+    LoadAudioPattern,
+    SetPitch {
+        register_num: u8,
+    },
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
@@ -104,6 +173,18 @@ pub enum InstructionDecodeError {
     UnknownInstruction(u16),
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
+pub enum InstructionEncodeError {
+    #[error("Register out of range: {0}")]
+    RegisterOutOfRange(u8),
+    #[error("Address out of range: {0:#x}")]
+    AddressOutOfRange(u16),
+    #[error("Operand doesn't fit in a nibble: {0}")]
+    NibbleOutOfRange(u8),
+    #[error("Instruction takes more than one opcode: {0:?}")]
+    LongInstruction(Instruction),
+}
+
 impl TryFrom<u16> for Instruction {
     type Error = InstructionDecodeError;
 
@@ -136,13 +217,20 @@ impl TryFrom<u16> for Instruction {
             }
             0x5000 => {
                 let (x_reg_num, y_reg_num, last_nibble) = separate_two_registers_and_nibble(opcode);
-                if last_nibble == 0 {
-                    Ok(Instruction::SkipIfEqualV2 {
+                match last_nibble {
+                    0 => Ok(Instruction::SkipIfEqualV2 {
                         x_reg_num,
                         y_reg_num,
-                    })
-                } else {
-                    Err(InstructionDecodeError::UnknownInstruction(opcode))
+                    }),
+                    2 => Ok(Instruction::SaveRegisterRange {
+                        x_reg_num,
+                        y_reg_num,
+                    }),
+                    3 => Ok(Instruction::LoadRegisterRange {
+                        x_reg_num,
+                        y_reg_num,
+                    }),
+                    _ => Err(InstructionDecodeError::UnknownInstruction(opcode)),
                 }
             }
             0x6000 => {
@@ -211,6 +299,14 @@ impl TryFrom<u16> for Instruction {
                 let imm = opcode & 0xFFF;
                 Ok(Instruction::LoadIImm { imm })
             }
+            0xB000 => {
+                let address = Address(opcode & 0x0FFF);
+                Ok(Instruction::JumpWithOffset { address })
+            }
+            0xC000 => {
+                let (reg_num, imm) = separate_register_and_imm(opcode);
+                Ok(Instruction::LoadVRandom { reg_num, imm })
+            }
             0xD000 => {
                 let (x_reg_num, y_reg_num, sprite_length) =
                     separate_two_registers_and_nibble(opcode);
@@ -220,6 +316,14 @@ impl TryFrom<u16> for Instruction {
                     sprite_length,
                 })
             }
+            0xE000 => {
+                let (register_num, specifier) = separate_register_and_imm(opcode);
+                match specifier {
+                    0x9E => Ok(Instruction::SkipIfKeyPressed { register_num }),
+                    0xA1 => Ok(Instruction::SkipIfKeyNotPressed { register_num }),
+                    _ => Err(InstructionDecodeError::UnknownInstruction(opcode)),
+                }
+            }
             0xF000 => {
                 let (register_num, specifier) = separate_register_and_imm(opcode);
                 match specifier {
@@ -231,18 +335,238 @@ impl TryFrom<u16> for Instruction {
                     }),
                     0x33 => Ok(Instruction::BCDRegister { register_num }),
                     0x1E => Ok(Instruction::AddIV { register_num }),
+                    0x07 => Ok(Instruction::LoadVDelayTimer { register_num }),
+                    0x0A => Ok(Instruction::WaitForKey { register_num }),
+                    0x29 => Ok(Instruction::LoadISprite { register_num }),
+                    0x30 => Ok(Instruction::LoadIBigSprite { register_num }),
+                    0x75 => Ok(Instruction::SaveRegistersToFlags {
+                        max_reg_num: register_num,
+                    }),
+                    0x85 => Ok(Instruction::LoadRegistersFromFlags {
+                        max_reg_num: register_num,
+                    }),
+                    0x02 if register_num == 0 => Ok(Instruction::LoadAudioPattern),
+                    0x3A => Ok(Instruction::SetPitch { register_num }),
+                    0x01 => Ok(Instruction::SelectPlanes {
+                        planes: register_num,
+                    }),
+                    0x15 => Ok(Instruction::SetDelayTimer { register_num }),
+                    0x18 => Ok(Instruction::SetSoundTimer { register_num }),
                     _ => Err(InstructionDecodeError::UnknownInstruction(opcode)),
                 }
             }
-            0x0000 => {
-                let address = Address(opcode & 0x0FFF);
-                Ok(Instruction::Sys { address })
-            }
+            0x0000 => match opcode {
+                0x00FB => Ok(Instruction::ScrollRight),
+                0x00FC => Ok(Instruction::ScrollLeft),
+                0x00FD => Ok(Instruction::Exit),
+                0x00FE => Ok(Instruction::LowRes),
+                0x00FF => Ok(Instruction::HighRes),
+                _ if opcode & 0xFFF0 == 0x00C0 => Ok(Instruction::ScrollDown {
+                    rows: (opcode & 0x000F) as u8,
+                }),
+                _ if opcode & 0xFFF0 == 0x00D0 => Ok(Instruction::ScrollUp {
+                    rows: (opcode & 0x000F) as u8,
+                }),
+                _ => {
+                    let address = Address(opcode & 0x0FFF);
+                    Ok(Instruction::Sys { address })
+                }
+            },
             _ => Err(InstructionDecodeError::UnknownInstruction(opcode)),
         }
     }
 }
 
+impl TryFrom<Instruction> for u16 {
+    type Error = InstructionEncodeError;
+
+    /// Encodes an instruction as its opcode, checking its operands fit
+    /// ```
+    /// # use eoxchip8::core::cpu::instructions::{Instruction, InstructionEncodeError};
+    /// let instruction = Instruction::AddVImm { reg_num: 3, imm: 1 };
+    /// assert_eq!(u16::try_from(instruction), Ok(0x7301));
+    /// let instruction = Instruction::AddVImm { reg_num: 16, imm: 1 };
+    /// assert_eq!(
+    ///     u16::try_from(instruction),
+    ///     Err(InstructionEncodeError::RegisterOutOfRange(16))
+    /// );
+    /// ```
+    #[allow(clippy::too_many_lines)]
+    fn try_from(instruction: Instruction) -> Result<Self, Self::Error> {
+        match instruction {
+            Instruction::ClearScreen => Ok(0x00E0),
+            Instruction::Return => Ok(0x00EE),
+            Instruction::ScrollRight => Ok(0x00FB),
+            Instruction::ScrollLeft => Ok(0x00FC),
+            Instruction::Exit => Ok(0x00FD),
+            Instruction::LowRes => Ok(0x00FE),
+            Instruction::HighRes => Ok(0x00FF),
+            Instruction::LoadAudioPattern => Ok(0xF002),
+            Instruction::ScrollDown { rows } => Ok(0x00C0 | encode_nibble(rows)?),
+            Instruction::ScrollUp { rows } => Ok(0x00D0 | encode_nibble(rows)?),
+            Instruction::Sys { address } => encode_address(0x0000, address.0),
+            Instruction::JumpTo { address } => encode_address(0x1000, address.0),
+            Instruction::Call { address } => encode_address(0x2000, address.0),
+            Instruction::LoadIImm { imm } => encode_address(0xA000, imm),
+            Instruction::JumpWithOffset { address } => encode_address(0xB000, address.0),
+            Instruction::SkipIfEqVImm { reg_num, imm } => {
+                encode_register_and_imm(0x3000, reg_num, imm)
+            }
+            Instruction::SkipIfNotEqVImm { reg_num, imm } => {
+                encode_register_and_imm(0x4000, reg_num, imm)
+            }
+            Instruction::LoadVImm { reg_num, imm } => encode_register_and_imm(0x6000, reg_num, imm),
+            Instruction::AddVImm { reg_num, imm } => encode_register_and_imm(0x7000, reg_num, imm),
+            Instruction::LoadVRandom { reg_num, imm } => {
+                encode_register_and_imm(0xC000, reg_num, imm)
+            }
+            Instruction::SkipIfEqualV2 {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x5000, x_reg_num, y_reg_num, 0x0),
+            Instruction::SaveRegisterRange {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x5000, x_reg_num, y_reg_num, 0x2),
+            Instruction::LoadRegisterRange {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x5000, x_reg_num, y_reg_num, 0x3),
+            Instruction::SetEqual {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x0),
+            Instruction::BitWiseOrEqual {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x1),
+            Instruction::BitWiseAndEqual {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x2),
+            Instruction::BitWiseXorEqual {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x3),
+            Instruction::AddV2 {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x4),
+            Instruction::SubV2 {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x5),
+            Instruction::ShiftRight {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x6),
+            Instruction::SubNV2 {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0x7),
+            Instruction::ShiftLeft {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x8000, x_reg_num, y_reg_num, 0xE),
+            Instruction::SkipIfNotEqualV2 {
+                x_reg_num,
+                y_reg_num,
+            } => encode_two_registers_and_nibble(0x9000, x_reg_num, y_reg_num, 0x0),
+            Instruction::Draw {
+                x_reg_num,
+                y_reg_num,
+                sprite_length,
+            } => encode_two_registers_and_nibble(0xD000, x_reg_num, y_reg_num, sprite_length),
+            Instruction::SkipIfKeyPressed { register_num } => {
+                encode_register_and_imm(0xE000, register_num, 0x9E)
+            }
+            Instruction::SkipIfKeyNotPressed { register_num } => {
+                encode_register_and_imm(0xE000, register_num, 0xA1)
+            }
+            Instruction::SelectPlanes { planes } => Ok(0xF001 | encode_nibble(planes)? << 8),
+            Instruction::LoadVDelayTimer { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x07)
+            }
+            Instruction::WaitForKey { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x0A)
+            }
+            Instruction::SetDelayTimer { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x15)
+            }
+            Instruction::SetSoundTimer { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x18)
+            }
+            Instruction::AddIV { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x1E)
+            }
+            Instruction::LoadISprite { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x29)
+            }
+            Instruction::LoadIBigSprite { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x30)
+            }
+            Instruction::BCDRegister { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x33)
+            }
+            Instruction::SetPitch { register_num } => {
+                encode_register_and_imm(0xF000, register_num, 0x3A)
+            }
+            Instruction::SaveRegistersToMem { max_reg_num } => {
+                encode_register_and_imm(0xF000, max_reg_num, 0x55)
+            }
+            Instruction::LoadRegistersFromMem { max_reg_num } => {
+                encode_register_and_imm(0xF000, max_reg_num, 0x65)
+            }
+            Instruction::SaveRegistersToFlags { max_reg_num } => {
+                encode_register_and_imm(0xF000, max_reg_num, 0x75)
+            }
+            Instruction::LoadRegistersFromFlags { max_reg_num } => {
+                encode_register_and_imm(0xF000, max_reg_num, 0x85)
+            }
+            Instruction::LoadILong { .. } => {
+                Err(InstructionEncodeError::LongInstruction(instruction))
+            }
+        }
+    }
+}
+
+fn encode_nibble(nibble: u8) -> Result<u16, InstructionEncodeError> {
+    if nibble > 0xF {
+        return Err(InstructionEncodeError::NibbleOutOfRange(nibble));
+    }
+    Ok(u16::from(nibble))
+}
+
+fn encode_register(reg_num: u8) -> Result<u16, InstructionEncodeError> {
+    if reg_num > 0xF {
+        return Err(InstructionEncodeError::RegisterOutOfRange(reg_num));
+    }
+    Ok(u16::from(reg_num))
+}
+
+fn encode_address(base: u16, address: u16) -> Result<u16, InstructionEncodeError> {
+    if address > 0xFFF {
+        return Err(InstructionEncodeError::AddressOutOfRange(address));
+    }
+    Ok(base | address)
+}
+
+fn encode_register_and_imm(base: u16, reg_num: u8, imm: u8) -> Result<u16, InstructionEncodeError> {
+    Ok(base | encode_register(reg_num)? << 8 | u16::from(imm))
+}
+
+fn encode_two_registers_and_nibble(
+    base: u16,
+    x_reg_num: u8,
+    y_reg_num: u8,
+    nibble: u8,
+) -> Result<u16, InstructionEncodeError> {
+    Ok(base
+        | encode_register(x_reg_num)? << 8
+        | encode_register(y_reg_num)? << 4
+        | encode_nibble(nibble)?)
+}
+
 fn separate_register_and_imm(opcode: u16) -> (u8, u8) {
     let register_index = ((opcode & 0x0F00) >> 8) as u8;
     let immediate = (opcode & 0x00FF) as u8;
@@ -255,3 +579,51 @@ fn separate_two_registers_and_nibble(opcode: u16) -> (u8, u8, u8) {
     let nibble = (opcode & 0x000F) as u8;
     (register_index_1, register_index_2, nibble)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_round_trips_every_opcode() {
+        for opcode in 0..=u16::MAX {
+            if let Ok(instruction) = Instruction::try_from(opcode) {
+                assert_eq!(
+                    u16::try_from(instruction),
+                    Ok(opcode),
+                    "{opcode:#06x} decoded to {instruction:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_checks_operands() {
+        assert_eq!(
+            u16::try_from(Instruction::JumpTo {
+                address: Address(0x1000)
+            }),
+            Err(InstructionEncodeError::AddressOutOfRange(0x1000))
+        );
+        assert_eq!(
+            u16::try_from(Instruction::SetEqual {
+                x_reg_num: 1,
+                y_reg_num: 16
+            }),
+            Err(InstructionEncodeError::RegisterOutOfRange(16))
+        );
+        assert_eq!(
+            u16::try_from(Instruction::Draw {
+                x_reg_num: 0,
+                y_reg_num: 1,
+                sprite_length: 16
+            }),
+            Err(InstructionEncodeError::NibbleOutOfRange(16))
+        );
+        let long = Instruction::LoadILong { imm: 0x1234 };
+        assert_eq!(
+            u16::try_from(long),
+            Err(InstructionEncodeError::LongInstruction(long))
+        );
+    }
+}
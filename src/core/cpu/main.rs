@@ -1,59 +1,414 @@
 use log::debug;
 use thiserror::Error;
 
-use crate::core::memory::{
-    memory_to_flip_instructions, Address, Chip8Display, MemoryAccessError, Ram,
+use crate::core::{
+    audio::{samples_in_frame, Beeper, XoChipAudio, AUDIO_PATTERN_SIZE},
+    font::{FontSet, BIG_GLYPH_SIZE, DEFAULT_FONT_ADDRESS, GLYPH_SIZE, SUPER_CHIP_BIG_FONT},
+    keypad::{Keypad, KeypadError},
+    memory::{Address, Chip8Display, MemoryAccessError, Ram, DISPLAY_PLANES, PROGRAM_START},
+    platform::Platform,
+    rng::{RandomSource, XorShiftRng},
+    rpl::{MemoryRplStorage, RplStorage, RplStorageError},
 };
 
 use super::{
-    instructions::{Instruction, InstructionDecodeError},
-    registers::{RegisterI, RegisterPC, RegisterV},
+    instructions::{Instruction, InstructionDecodeError, LONG_LOAD_I_PREFIX},
+    quirks::{MemoryIncrement, Quirks},
+    registers::{RegisterI, RegisterPC, RegisterTimer, RegisterV},
+    stack::{CallStack, StackOverflowError},
+    timing::TimingModel,
 };
 
-#[derive(Default, Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
-pub struct Executor {
+/// Summary of a single frame run by [`Executor::run_frame`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub struct Frame {
+    /// Instructions executed, stopping early if execution blocked or halted
+    pub instructions: u32,
+    /// Cycles spent by those instructions under the executor's timing model
+    pub cycles: u32,
+    /// Whether the frame ended early on a sprite draw waiting for the vertical blank
+    pub waited_for_vblank: bool,
+    /// Whether the sound timer was active at the end of the frame, before it was ticked
+    pub sound_playing: bool,
+}
+
+/// Whether an [`Executor`] is able to run, and if not, what it's waiting on
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd, Ord, Eq)]
+pub enum RunState {
+    /// Executing instructions
+    #[default]
+    Running,
+    /// Blocked on FX0A until a key is released, which is stored in VX
+    WaitingForKey { register_num: u8 },
+    /// Blocked on a sprite draw until the next vertical blank, by the display wait quirk
+    WaitingForVBlank,
+    /// Stopped by the program, with 00FD
+    Halted,
+    /// Stopped by an error, which records the instruction that raised it
+    Faulted(ExecutionError),
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct Executor<R = XorShiftRng, S = MemoryRplStorage> {
     memory: Ram,
+    /// The program last loaded, kept to restart it on `reset`
+    program: Vec<u8>,
     gp_registers: [RegisterV; 16],
     display: Chip8Display,
     i: RegisterI,
     pc: RegisterPC,
-    stack: Vec<Address>,
-    legacy_shift: bool,
+    delay_timer: RegisterTimer,
+    sound_timer: RegisterTimer,
+    audio: XoChipAudio,
+    beeper: Beeper,
+    audio_frames: u64,
+    stack: CallStack,
+    /// Where the call stack is mirrored in RAM, if it is
+    stack_address: Option<Address>,
+    keypad: Keypad,
+    run_state: RunState,
+    /// Set by a vertical blank ending a wait, letting the stalled draw go ahead
+    vblank_passed: bool,
+    rng: R,
+    rpl_storage: S,
+    font_set: FontSet,
+    font_address: Address,
+    quirks: Quirks,
+    platform: Platform,
+    timing_model: TimingModel,
+    /// Cycles the last frame ran over its budget by, taken out of the next frame
+    cycle_debt: u32,
+}
+
+impl<R: RandomSource + Default, S: RplStorage + Default> Default for Executor<R, S> {
+    fn default() -> Self {
+        Executor::from_parts(Quirks::default(), R::default(), S::default())
+    }
 }
 
 impl Executor {
+    /// Creates an executor with a randomly seeded RNG
+    #[must_use]
+    pub fn new(quirks: Quirks) -> Self {
+        Executor::with_rng(quirks, XorShiftRng::from_entropy())
+    }
+
+    /// Creates an executor whose RNG always produces the same sequence for `seed`
     #[must_use]
-    pub fn new(legacy_shift: bool) -> Self {
+    pub fn with_seed(quirks: Quirks, seed: u64) -> Self {
+        Executor::with_rng(quirks, XorShiftRng::from_seed(seed))
+    }
+}
+
+impl<R: RandomSource> Executor<R> {
+    /// Creates an executor drawing CXNN results from `rng`
+    #[must_use]
+    pub fn with_rng(quirks: Quirks, rng: R) -> Self {
+        Executor::from_parts(quirks, rng, MemoryRplStorage::default())
+    }
+}
+
+impl<R: RandomSource, S: RplStorage> Executor<R, S> {
+    /// Creates an executor drawing CXNN results from `rng`, keeping RPL flags in `rpl_storage`
+    #[must_use]
+    pub fn from_parts(quirks: Quirks, rng: R, rpl_storage: S) -> Self {
         Executor {
-            legacy_shift,
-            ..Default::default()
+            memory: Ram::default(),
+            program: Vec::new(),
+            gp_registers: [RegisterV::default(); 16],
+            display: Chip8Display::default(),
+            i: RegisterI::default(),
+            pc: RegisterPC::default(),
+            delay_timer: RegisterTimer::default(),
+            sound_timer: RegisterTimer::default(),
+            audio: XoChipAudio::default(),
+            beeper: Beeper::default(),
+            audio_frames: 0,
+            stack: CallStack::new(quirks.stack_limit),
+            stack_address: None,
+            keypad: Keypad::default(),
+            run_state: RunState::default(),
+            vblank_passed: false,
+            rng,
+            rpl_storage,
+            font_set: FontSet::default(),
+            font_address: DEFAULT_FONT_ADDRESS,
+            quirks,
+            platform: Platform::default(),
+            timing_model: TimingModel::default(),
+            cycle_debt: 0,
         }
     }
 
+    #[must_use]
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    /// Selects the instruction set, takes full effect on the next `load_program`
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.platform = platform;
+    }
+
+    #[must_use]
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        self.stack.set_limit(quirks.stack_limit);
+    }
+
+    #[must_use]
+    pub fn timing_model(&self) -> TimingModel {
+        self.timing_model
+    }
+
+    /// Sets how instructions are costed against the budget given to `run_frame`
+    pub fn set_timing_model(&mut self, timing_model: TimingModel) {
+        self.timing_model = timing_model;
+        self.cycle_debt = 0;
+    }
+
+    /// Keeps the call stack in RAM at `stack_address`, or outside of RAM when `None`
+    ///
+    /// Each return address takes two bytes, with the outermost call first. Returns read their
+    /// address back from RAM, so programs can inspect and change where calls return to.
+    pub fn set_stack_address(&mut self, stack_address: Option<Address>) {
+        self.stack_address = stack_address;
+    }
+
+    /// Selects the font placed in the interpreter area on the next `load_program`
+    ///
+    /// The fonts must end by 0x200, or loading fails rather than overwrite the program.
+    pub fn set_font(&mut self, font_set: FontSet, font_address: Address) {
+        self.font_set = font_set;
+        self.font_address = font_address;
+    }
+
     pub fn load_program(&mut self, program: &[u8]) -> Result<(), MemoryAccessError> {
+        if self.font_end() > usize::from(PROGRAM_START.0) {
+            return Err(MemoryAccessError::FontOverlapsProgram(self.font_address));
+        }
+        self.memory = Ram::with_size(self.platform.memory_size());
         self.memory.load_program(program)?;
+        self.program = program.to_vec();
+        self.memory
+            .load_font(self.font_address, self.font_set.glyphs())?;
+        if self.platform.is_super_chip() {
+            self.memory
+                .load_font(self.big_font_address(), &SUPER_CHIP_BIG_FONT)?;
+        }
         self.pc = RegisterPC::default();
         self.display = Chip8Display::default();
         self.gp_registers = [RegisterV::default(); 16];
         self.i = RegisterI::default();
+        self.delay_timer = RegisterTimer::default();
+        self.sound_timer = RegisterTimer::default();
+        self.audio = XoChipAudio::default();
+        self.audio_frames = 0;
+        self.stack.clear();
+        self.keypad.clear();
+        self.run_state = RunState::Running;
+        self.vblank_passed = false;
+        self.cycle_debt = 0;
         Ok(())
     }
 
-    #[allow(clippy::too_many_lines)]
-    pub fn execute_once(&mut self) -> Result<(), ExecutionError> {
+    /// Restarts the last loaded program, as if it were loaded again
+    pub fn reset(&mut self) -> Result<(), MemoryAccessError> {
+        let program = std::mem::take(&mut self.program);
+        self.load_program(&program)
+    }
+
+    /// Counts the delay and sound timers down, should be called at 60 Hz
+    ///
+    /// This marks the vertical blank too, releasing any draw waiting on the display.
+    pub fn tick_timers(&mut self) {
+        self.delay_timer.tick();
+        self.sound_timer.tick();
+        if self.run_state == RunState::WaitingForVBlank {
+            self.run_state = RunState::Running;
+            self.vblank_passed = true;
+        }
+    }
+
+    /// Whether the sound timer is active, and the buzzer should sound
+    #[must_use]
+    pub fn is_sound_playing(&self) -> bool {
+        self.sound_timer.is_active()
+    }
+
+    /// Whether both the delay and sound timers have counted down to zero
+    #[must_use]
+    pub fn are_timers_idle(&self) -> bool {
+        !self.delay_timer.is_active() && !self.sound_timer.is_active()
+    }
+
+    /// Fills `samples` with the audio output at `sample_rate`, as signed 16 bit PCM
+    ///
+    /// XO-CHIP programs play their audio pattern, all others sound the beeper.
+    pub fn generate_audio(&mut self, sample_rate: u32, samples: &mut [i16]) {
+        let playing = self.is_sound_playing();
+        if self.platform.is_xo_chip() {
+            self.audio.generate(playing, sample_rate, samples);
+        } else {
+            self.beeper.generate(playing, sample_rate, samples);
+        }
+    }
+
+    /// Generates the audio output for one 60 Hz frame at `sample_rate`
+    ///
+    /// Call once per frame, alongside `tick_timers`, with the same sample rate each time.
+    pub fn audio_frame(&mut self, sample_rate: u32) -> Vec<i16> {
+        let mut samples = vec![0; samples_in_frame(sample_rate, self.audio_frames)];
+        self.audio_frames += 1;
+        self.generate_audio(sample_rate, &mut samples);
+        samples
+    }
+
+    #[must_use]
+    pub fn audio(&self) -> &XoChipAudio {
+        &self.audio
+    }
+
+    #[must_use]
+    pub fn beeper(&self) -> &Beeper {
+        &self.beeper
+    }
+
+    /// Replaces the beeper sounded by programs not written for XO-CHIP
+    pub fn set_beeper(&mut self, beeper: Beeper) {
+        self.beeper = beeper;
+    }
+
+    /// Marks a key on the keypad as held down
+    pub fn press_key(&mut self, key: u8) -> Result<(), KeypadError> {
+        self.keypad.press(key)
+    }
+
+    /// Marks a key on the keypad as released, completing any pending wait for a key
+    pub fn release_key(&mut self, key: u8) -> Result<(), KeypadError> {
+        let was_pressed = self.keypad.is_pressed(key)?;
+        self.keypad.release(key)?;
+        if was_pressed {
+            if let RunState::WaitingForKey { register_num } = self.run_state {
+                debug!("Key {key:#x} released, resuming execution");
+                self.gp_registers[register_num as usize].set(key);
+                self.run_state = RunState::Running;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether execution is blocked on FX0A, waiting for a key to be released
+    #[must_use]
+    pub fn is_waiting_for_key(&self) -> bool {
+        matches!(self.run_state, RunState::WaitingForKey { .. })
+    }
+
+    /// Whether a sprite draw is stalled until the next vertical blank, by the display wait quirk
+    #[must_use]
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        self.run_state == RunState::WaitingForVBlank
+    }
+
+    /// Whether the program has exited with 00FD
+    #[must_use]
+    pub fn is_halted(&self) -> bool {
+        self.run_state == RunState::Halted
+    }
+
+    /// Whether execution stopped on an error
+    #[must_use]
+    pub fn is_faulted(&self) -> bool {
+        matches!(self.run_state, RunState::Faulted(_))
+    }
+
+    #[must_use]
+    pub fn run_state(&self) -> &RunState {
+        &self.run_state
+    }
+
+    /// Continues execution after a halt or fault, from the instruction after the one that
+    /// stopped it
+    ///
+    /// A fault fetching an instruction, from outside memory or an odd address, leaves PC
+    /// where it was, so resuming faults again. Waits end on their own, so are left in place.
+    pub fn resume(&mut self) {
+        if self.is_halted() || self.is_faulted() {
+            self.run_state = RunState::Running;
+        }
+    }
+
+    /// Whether the instruction at PC is a jump to itself, the usual way of ending a program
+    #[must_use]
+    pub fn is_in_self_loop(&self) -> bool {
         let pc = self.pc.get();
+        self.peek(pc) == Some(Instruction::JumpTo { address: pc })
+    }
+
+    pub fn execute_once(&mut self) -> Result<(), ExecutionError> {
+        self.step().map(|_| ())
+    }
+
+    /// Executes the instruction at PC, returning it unless execution is blocked or stopped
+    ///
+    /// An error faults the executor, stopping it until it's resumed or reset.
+    fn step(&mut self) -> Result<Option<Instruction>, ExecutionError> {
+        if self.run_state != RunState::Running {
+            return Ok(None);
+        }
+        let instruction_address = self.pc.get();
+        self.execute(instruction_address).map_err(|kind| {
+            let error = ExecutionError {
+                kind,
+                pc: instruction_address,
+                opcode: self.memory.get_wide(instruction_address).ok(),
+                instruction: self.peek(instruction_address),
+                i: self.i.get(),
+                stack: self.stack.return_addresses().to_vec(),
+            };
+            self.run_state = RunState::Faulted(error.clone());
+            error
+        })
+    }
+
+    /// Executes the instruction at `instruction_address`, which PC points to
+    #[allow(clippy::too_many_lines)]
+    fn execute(
+        &mut self,
+        instruction_address: Address,
+    ) -> Result<Option<Instruction>, ExecutionErrorKind> {
         debug!("PC: {:?}", self.pc);
-        self.pc.inc();
-        let instruction = self.memory.get_wide(pc)?.try_into()?;
-        debug!("Instruction: {instruction:?}");
+        let instruction = self.fetch()?;
+        debug!("Instruction: {instruction}");
+        if !self.platform.supports(&instruction) {
+            return Err(ExecutionErrorKind::UnsupportedInstruction(instruction));
+        }
         match instruction {
             Instruction::ClearScreen => self.display.clear(),
             Instruction::Return => {
-                let return_address = self.stack.pop().ok_or(ExecutionError::StackPopFail)?;
+                let mut return_address =
+                    self.stack.pop().ok_or(ExecutionErrorKind::StackPopFail)?;
+                if let Some(stack_address) = self.stack_address {
+                    let slot = Self::stack_slot(stack_address, self.stack.depth());
+                    return_address = Address(self.memory.get_wide_unaligned(slot)?);
+                }
                 self.pc.set(return_address);
             }
             Instruction::Call { address } => {
-                self.stack.push(self.pc.get());
+                let return_address = self.pc.get();
+                let slot = self
+                    .stack_address
+                    .map(|stack_address| Self::stack_slot(stack_address, self.stack.depth()));
+                self.stack.push(return_address)?;
+                if let Some(slot) = slot {
+                    let [high, low] = return_address.0.to_be_bytes();
+                    self.memory.set(slot, high)?;
+                    self.memory.set(slot.offset(1), low)?;
+                }
                 self.pc.set(address);
             }
             Instruction::LoadVImm { reg_num, imm } => {
@@ -74,12 +429,12 @@ impl Executor {
             }
             Instruction::SkipIfEqVImm { reg_num, imm } => {
                 if self.gp_registers[reg_num as usize].get() == imm {
-                    self.pc.inc();
+                    self.skip_next_instruction();
                 }
             }
             Instruction::SkipIfNotEqVImm { reg_num, imm } => {
                 if self.gp_registers[reg_num as usize].get() != imm {
-                    self.pc.inc();
+                    self.skip_next_instruction();
                 }
             }
             Instruction::SkipIfEqualV2 {
@@ -89,7 +444,7 @@ impl Executor {
                 if self.gp_registers[x_reg_num as usize].get()
                     == self.gp_registers[y_reg_num as usize].get()
                 {
-                    self.pc.inc();
+                    self.skip_next_instruction();
                 }
             }
             Instruction::SkipIfNotEqualV2 {
@@ -99,7 +454,7 @@ impl Executor {
                 if self.gp_registers[x_reg_num as usize].get()
                     != self.gp_registers[y_reg_num as usize].get()
                 {
-                    self.pc.inc();
+                    self.skip_next_instruction();
                 }
             }
             Instruction::LoadIImm { imm } => {
@@ -110,6 +465,13 @@ impl Executor {
                 y_reg_num,
                 sprite_length,
             } => {
+                if self.quirks.display_wait && !self.vblank_passed {
+                    debug!("Waiting for the vertical blank to draw");
+                    self.pc.set(instruction_address);
+                    self.run_state = RunState::WaitingForVBlank;
+                    return Ok(None);
+                }
+                self.vblank_passed = false;
                 self.draw_on_display(x_reg_num, y_reg_num, sprite_length)?;
             }
             Instruction::JumpTo { address } => {
@@ -129,6 +491,7 @@ impl Executor {
                 let current_val = self.gp_registers[x_reg_num as usize].get();
                 let bit_or_val = self.gp_registers[y_reg_num as usize].get();
                 self.gp_registers[x_reg_num as usize].set(current_val | bit_or_val);
+                self.reset_flag_register_after_logic();
             }
             Instruction::BitWiseAndEqual {
                 x_reg_num,
@@ -137,6 +500,7 @@ impl Executor {
                 let current_val = self.gp_registers[x_reg_num as usize].get();
                 let bit_or_val = self.gp_registers[y_reg_num as usize].get();
                 self.gp_registers[x_reg_num as usize].set(current_val & bit_or_val);
+                self.reset_flag_register_after_logic();
             }
             Instruction::BitWiseXorEqual {
                 x_reg_num,
@@ -145,6 +509,7 @@ impl Executor {
                 let current_val = self.gp_registers[x_reg_num as usize].get();
                 let bit_or_val = self.gp_registers[y_reg_num as usize].get();
                 self.gp_registers[x_reg_num as usize].set(current_val ^ bit_or_val);
+                self.reset_flag_register_after_logic();
             }
             Instruction::AddV2 {
                 x_reg_num,
@@ -179,7 +544,7 @@ impl Executor {
                 x_reg_num,
                 y_reg_num,
             } => {
-                let used_register = if self.legacy_shift {
+                let used_register = if self.quirks.shift_uses_vy {
                     self.gp_registers[y_reg_num as usize].get()
                 } else {
                     self.gp_registers[x_reg_num as usize].get()
@@ -191,7 +556,7 @@ impl Executor {
                 x_reg_num,
                 y_reg_num,
             } => {
-                let used_register = if self.legacy_shift {
+                let used_register = if self.quirks.shift_uses_vy {
                     self.gp_registers[y_reg_num as usize].get()
                 } else {
                     self.gp_registers[x_reg_num as usize].get()
@@ -202,33 +567,173 @@ impl Executor {
             Instruction::LoadRegistersFromMem { max_reg_num } => {
                 let start_mem = self.i.get();
                 for offset in 0..=max_reg_num {
-                    self.gp_registers[offset as usize]
-                        .set(self.memory.get(Address(start_mem + u16::from(offset)))?);
+                    self.gp_registers[offset as usize].set(
+                        self.memory
+                            .get(Address(start_mem).offset(u16::from(offset)))?,
+                    );
                 }
+                self.increment_i_after_memory_access(max_reg_num);
             }
             Instruction::SaveRegistersToMem { max_reg_num } => {
                 let start_mem = self.i.get();
                 for offset in 0..=max_reg_num {
                     self.memory.set(
-                        Address(start_mem + u16::from(offset)),
+                        Address(start_mem).offset(u16::from(offset)),
                         self.gp_registers[offset as usize].get(),
                     )?;
                 }
+                self.increment_i_after_memory_access(max_reg_num);
             }
             Instruction::BCDRegister { register_num } => {
                 let (first_digit, second_digit, third_digit) =
-                    Executor::bcd(self.gp_registers[register_num as usize].get());
+                    Self::bcd(self.gp_registers[register_num as usize].get());
                 let root_address = self.i.get();
                 self.memory.set(Address(root_address), first_digit)?;
-                self.memory.set(Address(root_address + 1), second_digit)?;
-                self.memory.set(Address(root_address + 2), third_digit)?;
+                self.memory
+                    .set(Address(root_address).offset(1), second_digit)?;
+                self.memory
+                    .set(Address(root_address).offset(2), third_digit)?;
             }
             Instruction::AddIV { register_num } => {
                 self.i.add(self.gp_registers[register_num as usize].get());
+                if self.quirks.add_i_overflow_sets_vf {
+                    self.set_flag_register(self.i.get() > 0x0FFF);
+                }
+            }
+            Instruction::LoadVDelayTimer { register_num } => {
+                self.gp_registers[register_num as usize].set(self.delay_timer.get());
+            }
+            Instruction::SetDelayTimer { register_num } => {
+                self.delay_timer
+                    .set(self.gp_registers[register_num as usize].get());
+            }
+            Instruction::SetSoundTimer { register_num } => {
+                self.sound_timer
+                    .set(self.gp_registers[register_num as usize].get());
+            }
+            Instruction::SkipIfKeyPressed { register_num } => {
+                if self.is_key_in_register_pressed(register_num) {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::SkipIfKeyNotPressed { register_num } => {
+                if !self.is_key_in_register_pressed(register_num) {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::WaitForKey { register_num } => {
+                self.run_state = RunState::WaitingForKey { register_num };
+            }
+            Instruction::LoadVRandom { reg_num, imm } => {
+                let random = self.rng.next_byte();
+                self.gp_registers[reg_num as usize].set(random & imm);
+            }
+            Instruction::LoadISprite { register_num } => {
+                let digit = self.gp_registers[register_num as usize].get() & 0x0F;
+                self.i
+                    .set(self.font_address.0 + u16::from(digit * GLYPH_SIZE));
+            }
+            Instruction::JumpWithOffset { address } => {
+                // CHIP-48 and SUPER-CHIP read BXNN as XNN + VX, instead of NNN + V0
+                let offset_register = if self.quirks.jump_uses_vx {
+                    (address.0 >> 8) as usize
+                } else {
+                    0
+                };
+                let offset = self.gp_registers[offset_register].get();
+                self.pc.set(address.offset(u16::from(offset)));
+            }
+            Instruction::ScrollDown { rows } => self.display.scroll_down(rows),
+            Instruction::ScrollRight => self.display.scroll_right(4),
+            Instruction::ScrollLeft => self.display.scroll_left(4),
+            Instruction::Exit => self.run_state = RunState::Halted,
+            Instruction::LowRes => self.display.set_hires(false),
+            Instruction::HighRes => self.display.set_hires(true),
+            Instruction::LoadIBigSprite { register_num } => {
+                // The big font only has the digits 0 through 9, so A to F wrap around to them
+                // rather than point past the font
+                let digit = (self.gp_registers[register_num as usize].get() & 0x0F) % 10;
+                self.i
+                    .set(self.big_font_address().0 + u16::from(digit * BIG_GLYPH_SIZE));
+            }
+            Instruction::ScrollUp { rows } => self.display.scroll_up(rows),
+            Instruction::SaveRegisterRange {
+                x_reg_num,
+                y_reg_num,
+            } => {
+                let start_mem = Address(self.i.get());
+                for (offset, reg_num) in (0..).zip(Self::register_range(x_reg_num, y_reg_num)) {
+                    self.memory.set(
+                        start_mem.offset(offset),
+                        self.gp_registers[reg_num as usize].get(),
+                    )?;
+                }
+            }
+            Instruction::LoadRegisterRange {
+                x_reg_num,
+                y_reg_num,
+            } => {
+                let start_mem = Address(self.i.get());
+                for (offset, reg_num) in (0..).zip(Self::register_range(x_reg_num, y_reg_num)) {
+                    self.gp_registers[reg_num as usize]
+                        .set(self.memory.get(start_mem.offset(offset))?);
+                }
+            }
+            Instruction::SelectPlanes { planes } => self.display.select_planes(planes),
+            Instruction::LoadILong { imm } => self.i.set(imm),
+            Instruction::SaveRegistersToFlags { max_reg_num } => {
+                let mut flags = self.rpl_storage.load()?;
+                for reg_num in 0..=max_reg_num {
+                    flags[reg_num as usize] = self.gp_registers[reg_num as usize].get();
+                }
+                self.rpl_storage.save(&flags)?;
+            }
+            Instruction::LoadRegistersFromFlags { max_reg_num } => {
+                let flags = self.rpl_storage.load()?;
+                for reg_num in 0..=max_reg_num {
+                    self.gp_registers[reg_num as usize].set(flags[reg_num as usize]);
+                }
+            }
+            Instruction::LoadAudioPattern => {
+                let start_mem = Address(self.i.get());
+                let mut pattern = [0; AUDIO_PATTERN_SIZE];
+                for (offset, byte) in (0..).zip(pattern.iter_mut()) {
+                    *byte = self.memory.get(start_mem.offset(offset))?;
+                }
+                self.audio.set_pattern(pattern);
+            }
+            Instruction::SetPitch { register_num } => {
+                self.audio
+                    .set_pitch(self.gp_registers[register_num as usize].get());
             }
             Instruction::Sys { .. } => {}
         }
-        Ok(())
+        Ok(Some(instruction))
+    }
+
+    /// Runs instructions costing up to `cycles_per_frame` cycles, then ticks the timers once
+    ///
+    /// Instructions are costed by the timing model, and the last one may run past the end
+    /// of the budget, in which case the overrun is taken out of the next frame's budget.
+    /// Stops executing early if the program halts, or starts waiting for a key or the vertical
+    /// blank, but still ticks the timers, so a frame always spans the same amount of emulated
+    /// time.
+    pub fn run_frame(&mut self, cycles_per_frame: u32) -> Result<Frame, ExecutionError> {
+        let mut frame = Frame::default();
+        let budget = cycles_per_frame.saturating_sub(self.cycle_debt);
+        self.cycle_debt = self.cycle_debt.saturating_sub(cycles_per_frame);
+        while frame.cycles < budget {
+            let Some(instruction) = self.step()? else {
+                break;
+            };
+            frame.instructions += 1;
+            frame.cycles += self.timing_model.cost(&instruction);
+        }
+        self.cycle_debt += frame.cycles.saturating_sub(budget);
+        frame.waited_for_vblank = self.is_waiting_for_vblank();
+        frame.sound_playing = self.is_sound_playing();
+        self.tick_timers();
+        Ok(frame)
     }
 
     #[must_use]
@@ -236,43 +741,164 @@ impl Executor {
         &mut self.display
     }
 
+    /// Reads the instruction at PC, advancing PC past it
+    fn fetch(&mut self) -> Result<Instruction, ExecutionErrorKind> {
+        let opcode = self.memory.get_wide(self.pc.get())?;
+        self.pc.inc();
+        if opcode == LONG_LOAD_I_PREFIX && self.platform.is_xo_chip() {
+            let imm = self.memory.get_wide(self.pc.get())?;
+            self.pc.inc();
+            return Ok(Instruction::LoadILong { imm });
+        }
+        Ok(opcode.try_into()?)
+    }
+
+    /// Where the return address of the call `depth` calls deep is kept, in a stack in RAM
+    #[allow(clippy::cast_possible_truncation)]
+    fn stack_slot(stack_address: Address, depth: usize) -> Address {
+        stack_address.offset((depth as u16).wrapping_mul(2))
+    }
+
+    /// Decodes the instruction at `address` without executing it
+    fn peek(&self, address: Address) -> Option<Instruction> {
+        let opcode = self.memory.get_wide(address).ok()?;
+        if opcode == LONG_LOAD_I_PREFIX && self.platform.is_xo_chip() {
+            let imm = self.memory.get_wide(address.offset(2)).ok()?;
+            return Some(Instruction::LoadILong { imm });
+        }
+        opcode.try_into().ok()
+    }
+
+    /// Skips the instruction at PC, which on XO-CHIP may be the four byte F000 NNNN
+    fn skip_next_instruction(&mut self) {
+        if self.platform.is_xo_chip()
+            && self.memory.get_wide(self.pc.get()) == Ok(LONG_LOAD_I_PREFIX)
+        {
+            self.pc.inc();
+        }
+        self.pc.inc();
+    }
+
+    /// The registers from X to Y, in descending order if X is past Y
+    fn register_range(x_reg_num: u8, y_reg_num: u8) -> impl Iterator<Item = u8> {
+        (0..=x_reg_num.abs_diff(y_reg_num)).map(move |offset| {
+            if x_reg_num <= y_reg_num {
+                x_reg_num + offset
+            } else {
+                x_reg_num - offset
+            }
+        })
+    }
+
+    /// The address just past the fonts, which must be loaded below the program
+    fn font_end(&self) -> usize {
+        let mut end = usize::from(self.font_address.0) + self.font_set.glyphs().len();
+        if self.platform.is_super_chip() {
+            end += SUPER_CHIP_BIG_FONT.len();
+        }
+        end
+    }
+
+    /// The SUPER-CHIP large font is placed directly after the small font
     #[allow(clippy::cast_possible_truncation)]
+    fn big_font_address(&self) -> Address {
+        Address(self.font_address.0 + self.font_set.glyphs().len() as u16)
+    }
+
+    /// Draws a sprite at (VX, VY), setting VF if any lit pixel was turned off
+    ///
+    /// On SUPER-CHIP, a sprite length of 0 draws a 16x16 sprite, stored as two bytes per row.
+    /// On XO-CHIP, each selected plane is drawn with its own sprite, stored one after another.
     fn draw_on_display(
         &mut self,
         x_reg_num: u8,
         y_reg_num: u8,
         sprite_length: u8,
     ) -> Result<(), MemoryAccessError> {
-        let sprite_memory_start = self.i.get();
-        let mut sprite_direct_memory = vec![];
-        for offset in 0..sprite_length {
-            sprite_direct_memory.push(
-                self.memory
-                    .get(Address(sprite_memory_start + u16::from(offset)))?,
-            );
+        let wide = sprite_length == 0 && self.platform.is_super_chip();
+        let sprite_size = if wide { 32 } else { u16::from(sprite_length) };
+        let x_len = self.display.x_len();
+        let y_len = self.display.y_len();
+        // The starting position always wraps, only the sprite body can be clipped
+        let start_x = self.gp_registers[x_reg_num as usize].get() % x_len;
+        let start_y = self.gp_registers[y_reg_num as usize].get() % y_len;
+
+        let mut sprite_memory_start = Address(self.i.get());
+        let mut collision = false;
+        for plane in 0..DISPLAY_PLANES {
+            if self.display.selected_planes() & (1 << plane) == 0 {
+                continue;
+            }
+            collision |= self.draw_sprite_plane(
+                plane,
+                sprite_memory_start,
+                (start_x, start_y),
+                wide,
+                sprite_length,
+            )?;
+            sprite_memory_start = sprite_memory_start.offset(sprite_size);
         }
+        self.set_flag_register(collision);
+        Ok(())
+    }
 
-        let sprite_flips = memory_to_flip_instructions(&sprite_direct_memory);
+    /// Draws one plane of a sprite, returning whether any lit pixel was turned off
+    fn draw_sprite_plane(
+        &mut self,
+        plane: usize,
+        sprite_memory_start: Address,
+        (start_x, start_y): (u8, u8),
+        wide: bool,
+        sprite_length: u8,
+    ) -> Result<bool, MemoryAccessError> {
+        let (sprite_width, sprite_length) = if wide { (16, 16) } else { (8, sprite_length) };
+        let x_len = self.display.x_len();
+        let y_len = self.display.y_len();
 
-        let start_x = self.gp_registers[x_reg_num as usize].get() % self.display.x_len();
-        let start_y = self.gp_registers[y_reg_num as usize].get() % self.display.y_len();
-        for offset_x in 0..(u8::BITS as u8) {
-            if (start_x + offset_x) >= self.display.x_len() {
+        let mut collision = false;
+        for offset_y in 0..sprite_length {
+            let Some(y) = self.sprite_coordinate(start_y, offset_y, y_len) else {
                 break;
-            }
-
-            for offset_y in 0..sprite_length {
-                if (start_y + offset_y) >= self.display.y_len() {
-                    break;
-                }
+            };
+            let sprite_row = if wide {
+                self.memory
+                    .get_wide_unaligned(sprite_memory_start.offset(2 * u16::from(offset_y)))?
+            } else {
+                u16::from(
+                    self.memory
+                        .get(sprite_memory_start.offset(u16::from(offset_y)))?,
+                ) << 8
+            };
 
-                if sprite_flips[offset_y as usize][offset_x as usize] {
-                    self.display
-                        .flip_pixel(start_x + offset_x, start_y + offset_y);
+            for offset_x in 0..sprite_width {
+                if sprite_row & (0x8000 >> offset_x) == 0 {
+                    continue;
                 }
+                let Some(x) = self.sprite_coordinate(start_x, offset_x, x_len) else {
+                    break;
+                };
+                collision |= self.display.flip_plane_pixel(plane, x, y);
             }
         }
-        Ok(())
+        Ok(collision)
+    }
+
+    /// Resolves a pixel coordinate of a sprite, or `None` if it is clipped
+    fn sprite_coordinate(&self, start: u8, offset: u8, len: u8) -> Option<u8> {
+        let coordinate = start + offset;
+        if coordinate < len {
+            Some(coordinate)
+        } else if self.quirks.wrap_sprites {
+            Some(coordinate % len)
+        } else {
+            None
+        }
+    }
+
+    fn is_key_in_register_pressed(&self, register_num: u8) -> bool {
+        // Only the low nibble selects a key, as on the original interpreter
+        let key = self.gp_registers[register_num as usize].get() & 0x0F;
+        self.keypad.is_pressed(key).unwrap_or(false)
     }
 
     fn bcd(data: u8) -> (u8, u8, u8) {
@@ -282,6 +908,20 @@ impl Executor {
         (first_digit, second_digit, third_digit)
     }
 
+    fn reset_flag_register_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.gp_registers[15].set(0);
+        }
+    }
+
+    fn increment_i_after_memory_access(&mut self, max_reg_num: u8) {
+        match self.quirks.memory_increment {
+            MemoryIncrement::Unchanged => {}
+            MemoryIncrement::ByX => self.i.add(max_reg_num),
+            MemoryIncrement::ByXPlusOne => self.i.add(max_reg_num + 1),
+        }
+    }
+
     fn set_flag_register(&mut self, flag: bool) {
         if flag {
             self.gp_registers[15].set(1);
@@ -291,12 +931,604 @@ impl Executor {
     }
 }
 
+/// An error raised by an instruction, with the state of the executor when it was raised
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
+#[error("{kind} at {:#05x}", .pc.0)]
+pub struct ExecutionError {
+    pub kind: ExecutionErrorKind,
+    /// Address of the instruction that raised the error
+    pub pc: Address,
+    /// The opcode at PC, if it could be read
+    pub opcode: Option<u16>,
+    /// The instruction at PC, if it could be decoded
+    pub instruction: Option<Instruction>,
+    pub i: u16,
+    /// Return addresses on the call stack, with the most recent call last
+    pub stack: Vec<Address>,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
-pub enum ExecutionError {
+pub enum ExecutionErrorKind {
     #[error("Erorr on accessing memory: '{0}'")]
     MemoryAccess(#[from] MemoryAccessError),
     #[error("Error on decoding instruction: '{0}'")]
     InstructionDecode(#[from] InstructionDecodeError),
     #[error("Issue popping the stack")]
     StackPopFail,
+    #[error("Instruction not supported on this platform: {0}")]
+    UnsupportedInstruction(Instruction),
+    #[error("Error on accessing RPL flags: '{0}'")]
+    RplStorage(#[from] RplStorageError),
+    /// The calls leading to the overflow are in [`ExecutionError::stack`]
+    #[error("Error on calling subroutine: '{0}'")]
+    StackOverflow(#[from] StackOverflowError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cpu::stack::COSMAC_VIP_STACK_ADDRESS;
+
+    fn executor_with_program(program: &[u8]) -> Executor {
+        let mut executor = Executor::with_seed(Quirks::default(), 1);
+        executor.load_program(program).unwrap();
+        executor
+    }
+
+    #[test]
+    fn test_delay_timer_round_trip() {
+        // V0 := 2; delay := V0; tick; V1 := delay
+        let mut executor = executor_with_program(&[0x60, 0x02, 0xF0, 0x15, 0xF1, 0x07]);
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        executor.tick_timers();
+        executor.execute_once().unwrap();
+        assert_eq!(executor.gp_registers[1].get(), 1);
+    }
+
+    #[test]
+    fn test_sound_timer_stops_at_zero() {
+        // V0 := 1; buzzer := V0
+        let mut executor = executor_with_program(&[0x60, 0x01, 0xF0, 0x18]);
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        assert!(executor.is_sound_playing());
+        executor.tick_timers();
+        assert!(!executor.is_sound_playing());
+        executor.tick_timers();
+        assert_eq!(executor.sound_timer.get(), 0);
+    }
+
+    #[test]
+    fn test_timers_idle_once_both_run_down() {
+        // V0 := 1; V1 := 2; buzzer := V0; delay := V1
+        let mut executor = executor_with_program(&[0x60, 0x01, 0x61, 0x02, 0xF0, 0x18, 0xF1, 0x15]);
+        assert!(executor.are_timers_idle());
+        for _ in 0..4 {
+            executor.execute_once().unwrap();
+        }
+        assert!(!executor.are_timers_idle());
+        executor.tick_timers();
+        assert!(!executor.are_timers_idle());
+        executor.tick_timers();
+        assert!(executor.are_timers_idle());
+    }
+
+    #[test]
+    fn test_skip_if_key_pressed() {
+        // V0 := 0xB; skip if key V0 pressed; V1 := 1; V2 := 1
+        let mut executor = executor_with_program(&[0x60, 0x0B, 0xE0, 0x9E, 0x61, 0x01, 0x62, 0x01]);
+        executor.press_key(0xB).unwrap();
+        for _ in 0..3 {
+            executor.execute_once().unwrap();
+        }
+        assert_eq!(executor.gp_registers[1].get(), 0);
+        assert_eq!(executor.gp_registers[2].get(), 1);
+    }
+
+    #[test]
+    fn test_wait_for_key_blocks_until_release() {
+        // V3 := key; V4 := 1
+        let mut executor = executor_with_program(&[0xF3, 0x0A, 0x64, 0x01]);
+        executor.execute_once().unwrap();
+        assert!(executor.is_waiting_for_key());
+        executor.press_key(0x7).unwrap();
+        executor.execute_once().unwrap();
+        assert!(executor.is_waiting_for_key());
+        assert_eq!(executor.gp_registers[4].get(), 0);
+        executor.release_key(0x7).unwrap();
+        assert!(!executor.is_waiting_for_key());
+        assert_eq!(executor.gp_registers[3].get(), 0x7);
+        executor.execute_once().unwrap();
+        assert_eq!(executor.gp_registers[4].get(), 1);
+    }
+
+    #[test]
+    fn test_font_sprite_lookup() {
+        // V0 := 0xA; i := hex V0
+        let mut executor = Executor::with_seed(Quirks::default(), 1);
+        executor.set_font(FontSet::CosmacVip, Address(0x100));
+        executor.load_program(&[0x60, 0x0A, 0xF0, 0x29]).unwrap();
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        assert_eq!(executor.i.get(), 0x100 + 0xA * 5);
+        assert_eq!(executor.memory.get(Address(0x100 + 0xB * 5)), Ok(0xF0));
+        assert_eq!(executor.memory.get(Address(0x101 + 0xB * 5)), Ok(0x50));
+    }
+
+    #[test]
+    fn test_font_must_fit_below_program() {
+        let mut executor = Executor::with_seed(Quirks::default(), 1);
+        executor.set_font(FontSet::Standard, Address(0x1B0));
+        assert_eq!(executor.load_program(&[0x00, 0xE0]), Ok(()));
+        executor.set_font(FontSet::Standard, Address(0x1F0));
+        assert_eq!(
+            executor.load_program(&[0x00, 0xE0]),
+            Err(MemoryAccessError::FontOverlapsProgram(Address(0x1F0)))
+        );
+        executor.set_font(FontSet::Standard, Address(0xFFF0));
+        assert_eq!(
+            executor.load_program(&[0x00, 0xE0]),
+            Err(MemoryAccessError::FontOverlapsProgram(Address(0xFFF0)))
+        );
+        // The SUPER-CHIP big font needs room after the small one
+        executor.set_platform(Platform::SuperChip);
+        executor.set_font(FontSet::Standard, Address(0x14C));
+        assert_eq!(executor.load_program(&[0x00, 0xE0]), Ok(()));
+        executor.set_font(FontSet::Standard, Address(0x14D));
+        assert_eq!(
+            executor.load_program(&[0x00, 0xE0]),
+            Err(MemoryAccessError::FontOverlapsProgram(Address(0x14D)))
+        );
+    }
+
+    #[test]
+    fn test_draw_sets_collision_flag() {
+        // i := 0x20A; sprite V0 V0 1; sprite V0 V0 1; 0x80
+        let program = [0xA2, 0x08, 0xD0, 0x01, 0xD0, 0x01, 0x00, 0x00, 0x80];
+        let mut executor = executor_with_program(&program);
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        assert!(executor.display.get()[0][0]);
+        assert_eq!(executor.gp_registers[15].get(), 0);
+        executor.execute_once().unwrap();
+        assert!(!executor.display.get()[0][0]);
+        assert_eq!(executor.gp_registers[15].get(), 1);
+    }
+
+    #[test]
+    fn test_draw_clips_or_wraps_at_edges() {
+        // V0 := 62; V1 := 31; i := 0x20A; sprite V0 V1 2; 0xF0 0xF0
+        let program = [
+            0x60, 0x3E, 0x61, 0x1F, 0xA2, 0x0A, 0xD0, 0x12, 0x00, 0x00, 0xF0, 0xF0,
+        ];
+        let mut clipped = executor_with_program(&program);
+        let mut wrapped = executor_with_program(&program);
+        wrapped.set_quirks(Quirks {
+            wrap_sprites: true,
+            ..Quirks::default()
+        });
+        for _ in 0..4 {
+            clipped.execute_once().unwrap();
+            wrapped.execute_once().unwrap();
+        }
+        assert!(clipped.display.get()[31][63]);
+        assert!(!clipped.display.get()[31][0]);
+        assert!(!clipped.display.get()[0][62]);
+        assert!(wrapped.display.get()[31][63]);
+        assert!(wrapped.display.get()[31][1]);
+        assert!(wrapped.display.get()[0][62]);
+        assert!(wrapped.display.get()[0][1]);
+        assert!(!wrapped.display.get()[0][2]);
+    }
+
+    #[test]
+    fn test_jump_with_offset_quirk() {
+        // V0 := 0x10; V3 := 0x20; jump0 0x300
+        let program = [0x60, 0x10, 0x63, 0x20, 0xB3, 0x00];
+        let mut cosmac = Executor::with_seed(Quirks::cosmac_vip(), 1);
+        let mut chip48 = Executor::with_seed(Quirks::chip48(), 1);
+        cosmac.load_program(&program).unwrap();
+        chip48.load_program(&program).unwrap();
+        for _ in 0..3 {
+            cosmac.execute_once().unwrap();
+            chip48.execute_once().unwrap();
+        }
+        assert_eq!(cosmac.pc.get(), Address(0x310));
+        assert_eq!(chip48.pc.get(), Address(0x320));
+    }
+
+    #[test]
+    fn test_logic_resets_vf_quirk() {
+        // VF := 1; V0 |= V1
+        let program = [0x6F, 0x01, 0x80, 0x11];
+        let mut cosmac = Executor::with_seed(Quirks::cosmac_vip(), 1);
+        let mut schip = Executor::with_seed(Quirks::super_chip_1_1(), 1);
+        cosmac.load_program(&program).unwrap();
+        schip.load_program(&program).unwrap();
+        for _ in 0..2 {
+            cosmac.execute_once().unwrap();
+            schip.execute_once().unwrap();
+        }
+        assert_eq!(cosmac.gp_registers[15].get(), 0);
+        assert_eq!(schip.gp_registers[15].get(), 1);
+    }
+
+    #[test]
+    fn test_memory_increment_quirk() {
+        // i := 0x300; save V2
+        let program = [0xA3, 0x00, 0xF2, 0x55];
+        for (quirks, expected_i) in [
+            (Quirks::cosmac_vip(), 0x303),
+            (Quirks::chip48(), 0x302),
+            (Quirks::super_chip_1_1(), 0x300),
+        ] {
+            let mut executor = Executor::with_seed(quirks, 1);
+            executor.load_program(&program).unwrap();
+            executor.execute_once().unwrap();
+            executor.execute_once().unwrap();
+            assert_eq!(executor.i.get(), expected_i);
+        }
+    }
+
+    #[test]
+    fn test_super_chip_instructions_need_platform() {
+        // hires
+        let mut executor = executor_with_program(&[0x00, 0xFF]);
+        assert_eq!(
+            executor.execute_once().unwrap_err().kind,
+            ExecutionErrorKind::UnsupportedInstruction(Instruction::HighRes)
+        );
+    }
+
+    #[test]
+    fn test_super_chip_hires_big_sprite() {
+        // hires; V0 := 120; V1 := 40; i := 0x20C; sprite V0 V1 0; exit; 0xFF 0xFF ...
+        let mut program = vec![
+            0x00, 0xFF, 0x60, 0x78, 0x61, 0x28, 0xA2, 0x0C, 0xD0, 0x10, 0x00, 0xFD,
+        ];
+        program.extend([0xFF; 32]);
+        let mut executor = Executor::with_seed(Quirks::super_chip_1_1(), 1);
+        executor.set_platform(Platform::SuperChip);
+        executor.load_program(&program).unwrap();
+        for _ in 0..6 {
+            executor.execute_once().unwrap();
+        }
+        assert!(executor.is_halted());
+        assert!(executor.display.is_hires());
+        assert!(executor.display.get()[40][127]);
+        assert!(executor.display.get()[40 + 15][127]);
+        assert!(!executor.display.get()[40 + 16][127]);
+        assert!(!executor.display.get()[40][0]);
+    }
+
+    #[test]
+    fn test_super_chip_big_font_lookup() {
+        // V0 := 3; i := bighex V0
+        let mut executor = Executor::with_seed(Quirks::super_chip_1_1(), 1);
+        executor.set_platform(Platform::SuperChip);
+        executor.load_program(&[0x60, 0x03, 0xF0, 0x30]).unwrap();
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        let glyph_start = DEFAULT_FONT_ADDRESS.0 + 80 + 30;
+        assert_eq!(executor.i.get(), glyph_start);
+        assert_eq!(executor.memory.get(Address(glyph_start)), Ok(0x3C));
+    }
+
+    #[test]
+    fn test_super_chip_big_font_wraps_hex_digits() {
+        // V0 := 0xC; i := bighex V0
+        let mut executor = Executor::with_seed(Quirks::super_chip_1_1(), 1);
+        executor.set_platform(Platform::SuperChip);
+        executor.load_program(&[0x60, 0x0C, 0xF0, 0x30]).unwrap();
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        assert_eq!(executor.i.get(), DEFAULT_FONT_ADDRESS.0 + 80 + 20);
+    }
+
+    fn xo_chip_executor_with_program(program: &[u8]) -> Executor {
+        let mut executor = Executor::with_seed(Quirks::xo_chip(), 1);
+        executor.set_platform(Platform::XoChip);
+        executor.load_program(program).unwrap();
+        executor
+    }
+
+    #[test]
+    fn test_xo_chip_long_load_and_skip() {
+        // i := long 0xF123; skip if V0 == 0; i := long 0x1234; V1 := 1
+        let mut executor = xo_chip_executor_with_program(&[
+            0xF0, 0x00, 0xF1, 0x23, 0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x01,
+        ]);
+        executor.execute_once().unwrap();
+        assert_eq!(executor.i.get(), 0xF123);
+        executor.execute_once().unwrap();
+        assert_eq!(executor.i.get(), 0xF123);
+        assert_eq!(executor.pc.get(), Address(0x20A));
+        executor.execute_once().unwrap();
+        assert_eq!(executor.gp_registers[1].get(), 1);
+        executor.memory.set(Address(0xF123), 0xAA).unwrap();
+        assert_eq!(executor.memory.get(Address(0xF123)), Ok(0xAA));
+    }
+
+    #[test]
+    fn test_xo_chip_register_ranges() {
+        // V1 := 1; V2 := 2; V3 := 3; i := 0x300; save V3 - V1; load V1 - V2
+        let mut executor = xo_chip_executor_with_program(&[
+            0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00, 0x53, 0x12, 0x51, 0x23,
+        ]);
+        for _ in 0..6 {
+            executor.execute_once().unwrap();
+        }
+        assert_eq!(executor.memory.get(Address(0x300)), Ok(3));
+        assert_eq!(executor.memory.get(Address(0x301)), Ok(2));
+        assert_eq!(executor.memory.get(Address(0x302)), Ok(1));
+        assert_eq!(executor.i.get(), 0x300);
+        assert_eq!(executor.gp_registers[1].get(), 3);
+        assert_eq!(executor.gp_registers[2].get(), 2);
+    }
+
+    #[test]
+    fn test_xo_chip_draws_each_selected_plane() {
+        // plane 3; i := 0x208; sprite V0 V0 1; 0x80 0x40
+        let mut executor = xo_chip_executor_with_program(&[
+            0xF3, 0x01, 0xA2, 0x08, 0xD0, 0x01, 0x00, 0x00, 0x80, 0x40,
+        ]);
+        for _ in 0..3 {
+            executor.execute_once().unwrap();
+        }
+        assert_eq!(executor.display.pixel(0, 0), 1);
+        assert_eq!(executor.display.pixel(1, 0), 2);
+    }
+
+    #[test]
+    fn test_rpl_flags_survive_reload() {
+        // V0 := 1; V1 := 2; saveflags V1; V0 := 0; V1 := 0; loadflags V0
+        let program = [
+            0x60, 0x01, 0x61, 0x02, 0xF1, 0x75, 0x60, 0x00, 0x61, 0x00, 0xF0, 0x85,
+        ];
+        let mut executor = Executor::with_seed(Quirks::super_chip_1_1(), 1);
+        executor.set_platform(Platform::SuperChip);
+        executor.load_program(&program).unwrap();
+        for _ in 0..6 {
+            executor.execute_once().unwrap();
+        }
+        assert_eq!(executor.gp_registers[0].get(), 1);
+        assert_eq!(executor.gp_registers[1].get(), 0);
+        executor.load_program(&program[6..]).unwrap();
+        for _ in 0..3 {
+            executor.execute_once().unwrap();
+        }
+        assert_eq!(executor.gp_registers[0].get(), 1);
+    }
+
+    #[test]
+    fn test_xo_chip_audio_pattern_and_pitch() {
+        // V0 := 112; pitch := V0; i := 0x20A; audio; buzzer := V0; 0xFF...
+        let mut program = vec![0x60, 0x70, 0xF0, 0x3A, 0xA2, 0x0A, 0xF0, 0x02, 0xF0, 0x18];
+        program.extend([0xFF; AUDIO_PATTERN_SIZE]);
+        let mut executor = xo_chip_executor_with_program(&program);
+        for _ in 0..5 {
+            executor.execute_once().unwrap();
+        }
+        assert_eq!(executor.audio().pitch(), 112);
+        assert_eq!(executor.audio().pattern(), &[0xFF; AUDIO_PATTERN_SIZE]);
+        let mut samples = [0; 4];
+        executor.generate_audio(8000, &mut samples);
+        assert!(samples.iter().all(|sample| *sample > 0));
+    }
+
+    #[test]
+    fn test_beeper_follows_sound_timer() {
+        // V0 := 1; buzzer := V0
+        let mut executor = executor_with_program(&[0x60, 0x01, 0xF0, 0x18]);
+        assert!(executor.audio_frame(6000).iter().all(|sample| *sample == 0));
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        let samples = executor.audio_frame(6000);
+        assert_eq!(samples.len(), 100);
+        assert!(samples.iter().any(|sample| *sample != 0));
+        executor.tick_timers();
+        assert!(executor.audio_frame(6000).iter().all(|sample| *sample == 0));
+    }
+
+    struct FixedRng(u8);
+
+    impl RandomSource for FixedRng {
+        fn next_byte(&mut self) -> u8 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_random_is_masked() {
+        // V5 := random 0x0F
+        let mut executor = Executor::with_rng(Quirks::default(), FixedRng(0xAB));
+        executor.load_program(&[0xC5, 0x0F]).unwrap();
+        executor.execute_once().unwrap();
+        assert_eq!(executor.gp_registers[5].get(), 0x0B);
+    }
+
+    #[test]
+    fn test_seeded_random_is_deterministic() {
+        let program = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF];
+        let mut first = Executor::with_seed(Quirks::default(), 1234);
+        let mut second = Executor::with_seed(Quirks::default(), 1234);
+        first.load_program(&program).unwrap();
+        second.load_program(&program).unwrap();
+        for _ in 0..3 {
+            first.execute_once().unwrap();
+            second.execute_once().unwrap();
+        }
+        assert_eq!(first.gp_registers, second.gp_registers);
+    }
+
+    #[test]
+    fn test_run_frame_ticks_timers_once() {
+        // V0 := 3; delay := V0; loop: jump loop
+        let mut executor = executor_with_program(&[0x60, 0x03, 0xF0, 0x15, 0x12, 0x04]);
+        let frame = executor.run_frame(10).unwrap();
+        assert_eq!(frame.cycles, 10);
+        assert_eq!(executor.delay_timer.get(), 2);
+        executor.run_frame(10).unwrap();
+        assert_eq!(executor.delay_timer.get(), 1);
+    }
+
+    #[test]
+    fn test_run_frame_stops_on_key_wait() {
+        // V0 := 1; V1 := key
+        let mut executor = executor_with_program(&[0x60, 0x01, 0xF1, 0x0A]);
+        let frame = executor.run_frame(10).unwrap();
+        assert_eq!(frame.instructions, 2);
+        assert!(executor.is_waiting_for_key());
+    }
+
+    #[test]
+    fn test_vip_timing_carries_overrun() {
+        // clear; loop: V0 := 1; jump loop
+        let mut executor = executor_with_program(&[0x00, 0xE0, 0x60, 0x01, 0x12, 0x02]);
+        executor.set_timing_model(TimingModel::CosmacVip);
+        let clear_cost = executor.timing_model.cost(&Instruction::ClearScreen);
+        let frame = executor.run_frame(clear_cost - 1).unwrap();
+        assert_eq!(frame.instructions, 1);
+        assert_eq!(frame.cycles, clear_cost);
+        // The single cycle of overrun comes out of the next frame
+        let load_cost = executor
+            .timing_model
+            .cost(&Instruction::LoadVImm { reg_num: 0, imm: 1 });
+        let frame = executor.run_frame(load_cost + 1).unwrap();
+        assert_eq!(frame.instructions, 1);
+        assert_eq!(frame.cycles, load_cost);
+    }
+
+    #[test]
+    fn test_display_wait_draws_once_per_frame() {
+        // i := font 0; loop: sprite V0 V1 5; V0 += 5; jump loop
+        let mut executor = executor_with_program(&[0xF0, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x12, 0x02]);
+        executor.set_quirks(Quirks {
+            display_wait: true,
+            ..Quirks::default()
+        });
+        let frame = executor.run_frame(100).unwrap();
+        assert_eq!(frame.instructions, 1);
+        assert!(frame.waited_for_vblank);
+        assert!(!executor.is_waiting_for_vblank());
+        assert!(!executor.display.get()[0][0]);
+        let frame = executor.run_frame(100).unwrap();
+        assert_eq!(frame.instructions, 3);
+        assert!(frame.waited_for_vblank);
+        assert!(executor.display.get()[0][0]);
+        assert!(!executor.display.get()[0][5]);
+    }
+
+    #[test]
+    fn test_fault_stops_until_resumed() {
+        // return; V0 := 1
+        let mut executor = executor_with_program(&[0x00, 0xEE, 0x60, 0x01]);
+        let error = executor.execute_once().unwrap_err();
+        assert_eq!(error.kind, ExecutionErrorKind::StackPopFail);
+        assert_eq!(executor.run_state(), &RunState::Faulted(error));
+        executor.execute_once().unwrap();
+        assert_eq!(executor.gp_registers[0].get(), 0);
+        executor.resume();
+        executor.execute_once().unwrap();
+        assert_eq!(executor.gp_registers[0].get(), 1);
+    }
+
+    #[test]
+    fn test_resume_after_fetch_fault_faults_again() {
+        // jump 0x201
+        let mut executor = executor_with_program(&[0x12, 0x01]);
+        executor.execute_once().unwrap();
+        let error = executor.execute_once().unwrap_err();
+        assert_eq!(
+            error.kind,
+            ExecutionErrorKind::MemoryAccess(MemoryAccessError::AddressUnaligned(Address(0x201)))
+        );
+        executor.resume();
+        assert_eq!(executor.execute_once(), Err(error));
+        assert_eq!(executor.pc.get(), Address(0x201));
+    }
+
+    #[test]
+    fn test_error_records_context() {
+        // call 0x204; 0x204: I := 0x123; unknown opcode E000
+        let mut executor = executor_with_program(&[0x22, 0x04, 0x00, 0x00, 0xA1, 0x23, 0xE0, 0x00]);
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        let error = executor.execute_once().unwrap_err();
+        assert_eq!(
+            error,
+            ExecutionError {
+                kind: ExecutionErrorKind::InstructionDecode(
+                    InstructionDecodeError::UnknownInstruction(0xE000)
+                ),
+                pc: Address(0x206),
+                opcode: Some(0xE000),
+                instruction: None,
+                i: 0x123,
+                stack: vec![Address(0x202)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        // loop: call loop
+        let mut executor = executor_with_program(&[0x22, 0x00]);
+        executor.set_quirks(Quirks::cosmac_vip());
+        for _ in 0..12 {
+            executor.execute_once().unwrap();
+        }
+        let error = executor.execute_once().unwrap_err();
+        assert_eq!(
+            error.kind,
+            ExecutionErrorKind::StackOverflow(StackOverflowError { limit: 12 })
+        );
+        assert_eq!(error.stack, vec![Address(0x202); 12]);
+    }
+
+    #[test]
+    fn test_stack_in_ram() {
+        // call 0x204; 0x204: I := 0xEA1; V0 := 0x0C; save V0; return; 0x20C: V1 := 1
+        let mut executor = executor_with_program(&[
+            0x22, 0x04, 0x00, 0x00, 0xAE, 0xA1, 0x60, 0x0C, 0xF0, 0x55, 0x00, 0xEE, 0x61, 0x01,
+        ]);
+        executor.set_stack_address(Some(COSMAC_VIP_STACK_ADDRESS));
+        executor.execute_once().unwrap();
+        assert_eq!(
+            executor.memory.get_wide(COSMAC_VIP_STACK_ADDRESS),
+            Ok(0x202)
+        );
+        // The program overwrites its return address, returning to 0x20C instead
+        for _ in 0..5 {
+            executor.execute_once().unwrap();
+        }
+        assert_eq!(executor.gp_registers[1].get(), 1);
+        assert_eq!(executor.pc.get(), Address(0x20E));
+    }
+
+    #[test]
+    fn test_reset_restarts_program() {
+        // V0 := 1; exit
+        let mut executor = xo_chip_executor_with_program(&[0x60, 0x01, 0x00, 0xFD]);
+        executor.execute_once().unwrap();
+        executor.execute_once().unwrap();
+        assert_eq!(executor.run_state(), &RunState::Halted);
+        executor.reset().unwrap();
+        assert_eq!(executor.run_state(), &RunState::Running);
+        assert_eq!(executor.gp_registers[0].get(), 0);
+        executor.execute_once().unwrap();
+        assert_eq!(executor.gp_registers[0].get(), 1);
+    }
+
+    #[test]
+    fn test_self_loop_detection() {
+        // V0 := 1; jump 0x202
+        let mut executor = executor_with_program(&[0x60, 0x01, 0x12, 0x02]);
+        assert!(!executor.is_in_self_loop());
+        executor.execute_once().unwrap();
+        assert!(executor.is_in_self_loop());
+        executor.execute_once().unwrap();
+        assert!(executor.is_in_self_loop());
+    }
 }
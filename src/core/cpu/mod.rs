@@ -1,3 +1,8 @@
+pub mod analysis;
+pub mod disassembler;
 pub mod instructions;
 pub mod main;
+pub mod quirks;
 pub mod registers;
+pub mod stack;
+pub mod timing;
@@ -0,0 +1,127 @@
+use super::stack::StackLimit;
+
+/// How FX55 and FX65 leave I after saving or loading registers
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub enum MemoryIncrement {
+    /// I is left untouched
+    #[default]
+    Unchanged,
+    /// I is advanced by X, as on the CHIP-48
+    ByX,
+    /// I is advanced past the last register, as on the COSMAC VIP
+    ByXPlusOne,
+}
+
+/// The behaviors that differ between Chip8 interpreters
+///
+/// The default matches most modern interpreters, see the presets for historical platforms.
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Quirks {
+    /// 8XY6 and 8XYE shift VY into VX, rather than shifting VX in place
+    pub shift_uses_vy: bool,
+    /// 8XY1, 8XY2 and 8XY3 reset VF to zero
+    pub logic_resets_vf: bool,
+    /// How FX55 and FX65 change I
+    pub memory_increment: MemoryIncrement,
+    /// Sprites wrap around the edges of the display, rather than being clipped
+    pub wrap_sprites: bool,
+    /// BXNN jumps to XNN + VX, rather than NNN + V0
+    pub jump_uses_vx: bool,
+    /// DXYN waits for the next vertical blank before drawing
+    pub display_wait: bool,
+    /// FX1E sets VF when I is pushed past 0xFFF
+    pub add_i_overflow_sets_vf: bool,
+    /// How deep 2NNN calls may nest
+    pub stack_limit: StackLimit,
+}
+
+/// Named interpreters with well known quirks
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub enum QuirkPreset {
+    CosmacVip,
+    Chip48,
+    SuperChip10,
+    SuperChip11,
+    XoChip,
+}
+
+impl Quirks {
+    /// The original interpreter on the RCA COSMAC VIP
+    #[must_use]
+    pub const fn cosmac_vip() -> Self {
+        Quirks {
+            shift_uses_vy: true,
+            logic_resets_vf: true,
+            memory_increment: MemoryIncrement::ByXPlusOne,
+            wrap_sprites: false,
+            jump_uses_vx: false,
+            display_wait: true,
+            add_i_overflow_sets_vf: false,
+            stack_limit: StackLimit::Depth(12),
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators
+    #[must_use]
+    pub const fn chip48() -> Self {
+        Quirks {
+            shift_uses_vy: false,
+            logic_resets_vf: false,
+            memory_increment: MemoryIncrement::ByX,
+            wrap_sprites: false,
+            jump_uses_vx: true,
+            display_wait: false,
+            add_i_overflow_sets_vf: false,
+            stack_limit: StackLimit::Depth(16),
+        }
+    }
+
+    /// SUPER-CHIP 1.0, which kept the CHIP-48 behaviors
+    #[must_use]
+    pub const fn super_chip_1_0() -> Self {
+        Quirks::chip48()
+    }
+
+    /// SUPER-CHIP 1.1, which stopped FX55 and FX65 from changing I
+    #[must_use]
+    pub const fn super_chip_1_1() -> Self {
+        Quirks {
+            memory_increment: MemoryIncrement::Unchanged,
+            ..Quirks::chip48()
+        }
+    }
+
+    /// XO-CHIP, as implemented by Octo
+    #[must_use]
+    pub const fn xo_chip() -> Self {
+        Quirks {
+            shift_uses_vy: true,
+            logic_resets_vf: false,
+            memory_increment: MemoryIncrement::ByXPlusOne,
+            wrap_sprites: true,
+            jump_uses_vx: false,
+            display_wait: false,
+            add_i_overflow_sets_vf: false,
+            stack_limit: StackLimit::Unlimited,
+        }
+    }
+}
+
+impl QuirkPreset {
+    /// Gets the quirks of this interpreter
+    /// ```
+    /// # use eoxchip8::core::cpu::quirks::{QuirkPreset, Quirks};
+    /// assert_eq!(QuirkPreset::CosmacVip.quirks(), Quirks::cosmac_vip());
+    /// ```
+    #[must_use]
+    pub const fn quirks(&self) -> Quirks {
+        match self {
+            QuirkPreset::CosmacVip => Quirks::cosmac_vip(),
+            QuirkPreset::Chip48 => Quirks::chip48(),
+            QuirkPreset::SuperChip10 => Quirks::super_chip_1_0(),
+            QuirkPreset::SuperChip11 => Quirks::super_chip_1_1(),
+            QuirkPreset::XoChip => Quirks::xo_chip(),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::{Display, Formatter};
 
-use crate::core::memory::Address;
+use crate::core::memory::{Address, PROGRAM_START};
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq)]
 pub struct RegisterV {
@@ -15,7 +15,7 @@ impl RegisterV {
 
     /// Gets the value in this register
     /// ```
-    /// # use rc8::core::cpu::registers::RegisterV;
+    /// # use eoxchip8::core::cpu::registers::RegisterV;
     /// # let register = RegisterV::new();
     /// assert_eq!(register.get(), 0);
     /// ````
@@ -26,7 +26,7 @@ impl RegisterV {
 
     /// Sets the value in this register
     /// ```
-    /// # use rc8::core::cpu::registers::RegisterV;
+    /// # use eoxchip8::core::cpu::registers::RegisterV;
     /// # let mut register = RegisterV::new();
     /// register.set(10);
     /// # assert_eq!(register.get(), 10);
@@ -67,7 +67,7 @@ impl RegisterI {
 
     /// Gets the value in this register
     /// ```
-    /// # use rc8::core::cpu::registers::RegisterI;
+    /// # use eoxchip8::core::cpu::registers::RegisterI;
     /// # let register = RegisterI::new();
     /// assert_eq!(register.get(), 0);
     /// ````
@@ -77,12 +77,12 @@ impl RegisterI {
     }
 
     pub fn add(&mut self, value: u8) {
-        self.data += u16::from(value);
+        self.data = self.data.wrapping_add(u16::from(value));
     }
 
     /// Sets the value in this register
     /// ```
-    /// # use rc8::core::cpu::registers::RegisterI;
+    /// # use eoxchip8::core::cpu::registers::RegisterI;
     /// # let mut register = RegisterI::new();
     /// register.set(10);
     /// # assert_eq!(register.get(), 10);
@@ -97,6 +97,60 @@ impl Display for RegisterI {
     }
 }
 
+#[derive(Default, Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct RegisterTimer {
+    data: u8,
+}
+
+impl RegisterTimer {
+    #[must_use]
+    pub fn new() -> Self {
+        RegisterTimer::default()
+    }
+
+    /// Gets the value in this timer
+    /// ```
+    /// # use eoxchip8::core::cpu::registers::RegisterTimer;
+    /// # let timer = RegisterTimer::new();
+    /// assert_eq!(timer.get(), 0);
+    /// ```
+    #[must_use]
+    pub fn get(&self) -> u8 {
+        self.data
+    }
+
+    /// Sets the value in this timer
+    pub fn set(&mut self, data: u8) {
+        self.data = data;
+    }
+
+    /// Counts this timer down by one, stopping at zero
+    /// ```
+    /// # use eoxchip8::core::cpu::registers::RegisterTimer;
+    /// # let mut timer = RegisterTimer::new();
+    /// timer.set(1);
+    /// timer.tick();
+    /// assert_eq!(timer.get(), 0);
+    /// timer.tick();
+    /// assert_eq!(timer.get(), 0);
+    /// ```
+    pub fn tick(&mut self) {
+        self.data = self.data.saturating_sub(1);
+    }
+
+    /// Whether this timer is still counting down
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.data != 0
+    }
+}
+
+impl Display for RegisterTimer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.data)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq)]
 pub struct RegisterPC {
     data: Address,
@@ -109,7 +163,7 @@ impl RegisterPC {
     }
 
     pub fn inc(&mut self) {
-        self.data.0 += 2;
+        self.data = self.data.offset(2);
     }
 
     #[must_use]
@@ -125,7 +179,7 @@ impl RegisterPC {
 impl Default for RegisterPC {
     fn default() -> Self {
         RegisterPC {
-            data: Address(0x200),
+            data: PROGRAM_START,
         }
     }
 }
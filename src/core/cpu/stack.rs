@@ -0,0 +1,88 @@
+use thiserror::Error;
+
+use crate::core::memory::Address;
+
+/// Where the COSMAC VIP interpreter keeps its call stack in RAM
+pub const COSMAC_VIP_STACK_ADDRESS: Address = Address(0xEA0);
+
+/// How many calls may be nested before the stack overflows
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub enum StackLimit {
+    /// Calls may nest as deep as memory allows
+    #[default]
+    Unlimited,
+    /// Calls may nest this deep, 12 on the COSMAC VIP and 16 on the HP-48 interpreters
+    Depth(u8),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Error)]
+#[error("Call stack overflowed, calls nested more than {limit} deep")]
+pub struct StackOverflowError {
+    pub limit: u8,
+}
+
+/// The return addresses of the calls in progress, bounded by a [`StackLimit`]
+#[derive(Default, Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct CallStack {
+    return_addresses: Vec<Address>,
+    limit: StackLimit,
+}
+
+impl CallStack {
+    #[must_use]
+    pub fn new(limit: StackLimit) -> Self {
+        CallStack {
+            return_addresses: Vec::new(),
+            limit,
+        }
+    }
+
+    #[must_use]
+    pub fn limit(&self) -> StackLimit {
+        self.limit
+    }
+
+    /// Changes the limit, without dropping any calls already past it
+    pub fn set_limit(&mut self, limit: StackLimit) {
+        self.limit = limit;
+    }
+
+    /// Pushes the return address of a call
+    /// ```
+    /// # use eoxchip8::core::{cpu::stack::*, memory::Address};
+    /// let mut stack = CallStack::new(StackLimit::Depth(1));
+    /// assert_eq!(stack.push(Address(0x202)), Ok(()));
+    /// assert_eq!(stack.push(Address(0x302)), Err(StackOverflowError { limit: 1 }));
+    /// assert_eq!(stack.return_addresses(), &[Address(0x202)]);
+    /// ```
+    pub fn push(&mut self, return_address: Address) -> Result<(), StackOverflowError> {
+        if let StackLimit::Depth(limit) = self.limit {
+            if self.depth() >= usize::from(limit) {
+                return Err(StackOverflowError { limit });
+            }
+        }
+        self.return_addresses.push(return_address);
+        Ok(())
+    }
+
+    /// Pops the return address of the innermost call, if there is one
+    pub fn pop(&mut self) -> Option<Address> {
+        self.return_addresses.pop()
+    }
+
+    /// The number of calls in progress
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.return_addresses.len()
+    }
+
+    /// The return addresses of the calls in progress, with the innermost call last
+    #[must_use]
+    pub fn return_addresses(&self) -> &[Address] {
+        &self.return_addresses
+    }
+
+    pub fn clear(&mut self) {
+        self.return_addresses.clear();
+    }
+}
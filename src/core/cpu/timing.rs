@@ -0,0 +1,126 @@
+use super::instructions::Instruction;
+
+/// Machine cycles the COSMAC VIP runs between two vertical blank interrupts
+///
+/// The VIP's 1.76 MHz clock takes 8 ticks per machine cycle, and the CDP1861 video chip
+/// interrupts at about 60 Hz, leaving this many cycles per frame.
+pub const COSMAC_VIP_CYCLES_PER_FRAME: u32 = 3668;
+
+/// Machine cycles the VIP interpreter spends fetching and decoding every instruction
+pub const COSMAC_VIP_FETCH_CYCLES: u32 = 40;
+
+/// How long each instruction takes to execute, and so how many fit in a frame
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub enum TimingModel {
+    /// Every instruction costs a single cycle, so frames are budgeted in instructions
+    #[default]
+    Uniform,
+    /// Instructions cost the machine cycles the COSMAC VIP interpreter took to run them
+    CosmacVip,
+}
+
+impl TimingModel {
+    /// The cycles `instruction` takes to execute under this model
+    #[must_use]
+    pub fn cost(self, instruction: &Instruction) -> u32 {
+        match self {
+            TimingModel::Uniform => 1,
+            TimingModel::CosmacVip => {
+                COSMAC_VIP_FETCH_CYCLES + cosmac_vip_execute_cycles(*instruction)
+            }
+        }
+    }
+
+    /// The cycles to budget each second for `instructions_per_second` under this model
+    ///
+    /// The VIP's speed is set by its hardware, `frame_rate` frames of its cycles a second, so
+    /// the requested speed only applies to uniform timing.
+    #[must_use]
+    pub fn cycles_per_second(self, instructions_per_second: u32, frame_rate: u32) -> u32 {
+        match self {
+            TimingModel::Uniform => instructions_per_second,
+            TimingModel::CosmacVip => COSMAC_VIP_CYCLES_PER_FRAME * frame_rate,
+        }
+    }
+}
+
+/// Machine cycles the VIP interpreter spends executing `instruction`, after fetching it
+///
+/// Costs which depend on the machine state, such as a skip being taken or a sprite straddling
+/// a byte boundary, are given for the common case: skips not taken and byte aligned sprites.
+/// Waiting for a key or the display is not included. Instructions the VIP didn't have only
+/// cost the fetch.
+fn cosmac_vip_execute_cycles(instruction: Instruction) -> u32 {
+    match instruction {
+        Instruction::ClearScreen => 3078 + 24,
+        Instruction::JumpTo { .. } | Instruction::LoadIImm { .. } => 12,
+        Instruction::Call { .. } => 26,
+        Instruction::JumpWithOffset { .. } => 22,
+        Instruction::LoadVImm { .. } => 6,
+        Instruction::Return
+        | Instruction::SkipIfEqVImm { .. }
+        | Instruction::SkipIfNotEqVImm { .. }
+        | Instruction::AddVImm { .. }
+        | Instruction::LoadVDelayTimer { .. }
+        | Instruction::SetDelayTimer { .. }
+        | Instruction::SetSoundTimer { .. } => 10,
+        Instruction::SkipIfEqualV2 { .. }
+        | Instruction::SkipIfNotEqualV2 { .. }
+        | Instruction::SkipIfKeyPressed { .. }
+        | Instruction::SkipIfKeyNotPressed { .. } => 14,
+        Instruction::SetEqual { .. }
+        | Instruction::BitWiseOrEqual { .. }
+        | Instruction::BitWiseAndEqual { .. }
+        | Instruction::BitWiseXorEqual { .. }
+        | Instruction::AddV2 { .. }
+        | Instruction::SubV2 { .. }
+        | Instruction::SubNV2 { .. }
+        | Instruction::ShiftRight { .. }
+        | Instruction::ShiftLeft { .. } => 44,
+        Instruction::LoadVRandom { .. } => 36,
+        Instruction::Draw { sprite_length, .. } => 68 + 46 * u32::from(sprite_length),
+        Instruction::AddIV { .. } | Instruction::LoadISprite { .. } => 16,
+        Instruction::WaitForKey { .. } => 18,
+        Instruction::BCDRegister { .. } => 152,
+        Instruction::SaveRegistersToMem { max_reg_num }
+        | Instruction::LoadRegistersFromMem { max_reg_num } => {
+            14 + 14 * (u32::from(max_reg_num) + 1)
+        }
+        Instruction::Sys { .. }
+        | Instruction::ScrollDown { .. }
+        | Instruction::ScrollUp { .. }
+        | Instruction::ScrollRight
+        | Instruction::ScrollLeft
+        | Instruction::Exit
+        | Instruction::LowRes
+        | Instruction::HighRes
+        | Instruction::LoadIBigSprite { .. }
+        | Instruction::SaveRegisterRange { .. }
+        | Instruction::LoadRegisterRange { .. }
+        | Instruction::SelectPlanes { .. }
+        | Instruction::LoadILong { .. }
+        | Instruction::SaveRegistersToFlags { .. }
+        | Instruction::LoadRegistersFromFlags { .. }
+        | Instruction::LoadAudioPattern
+        | Instruction::SetPitch { .. } => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vip_costs_vary_by_instruction() {
+        let load = Instruction::LoadVImm { reg_num: 0, imm: 1 };
+        let draw = Instruction::Draw {
+            x_reg_num: 0,
+            y_reg_num: 1,
+            sprite_length: 5,
+        };
+        assert_eq!(TimingModel::Uniform.cost(&load), 1);
+        assert_eq!(TimingModel::Uniform.cost(&draw), 1);
+        assert_eq!(TimingModel::CosmacVip.cost(&load), 46);
+        assert!(TimingModel::CosmacVip.cost(&draw) > 5 * TimingModel::CosmacVip.cost(&load));
+    }
+}
@@ -0,0 +1,58 @@
+use thiserror::Error;
+
+/// The state of the Chip8's 16 key hexadecimal keypad
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub struct Keypad {
+    keys: [bool; 16],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Error)]
+pub enum KeypadError {
+    #[error("Key out of range: {0:#x}")]
+    InvalidKey(u8),
+}
+
+type KeypadResult<T> = Result<T, KeypadError>;
+
+impl Keypad {
+    #[must_use]
+    pub fn new() -> Self {
+        Keypad::default()
+    }
+
+    /// Marks a key as held down
+    /// ```
+    /// # use eoxchip8::core::keypad::Keypad;
+    /// let mut keypad = Keypad::new();
+    /// keypad.press(0xA).unwrap();
+    /// assert_eq!(keypad.is_pressed(0xA), Ok(true));
+    /// ```
+    pub fn press(&mut self, key: u8) -> KeypadResult<()> {
+        *self.key_mut(key)? = true;
+        Ok(())
+    }
+
+    /// Marks a key as no longer held down
+    pub fn release(&mut self, key: u8) -> KeypadResult<()> {
+        *self.key_mut(key)? = false;
+        Ok(())
+    }
+
+    pub fn is_pressed(&self, key: u8) -> KeypadResult<bool> {
+        self.keys
+            .get(key as usize)
+            .copied()
+            .ok_or(KeypadError::InvalidKey(key))
+    }
+
+    /// Releases every key on the keypad
+    pub fn clear(&mut self) {
+        self.keys = [false; 16];
+    }
+
+    fn key_mut(&mut self, key: u8) -> KeypadResult<&mut bool> {
+        self.keys
+            .get_mut(key as usize)
+            .ok_or(KeypadError::InvalidKey(key))
+    }
+}
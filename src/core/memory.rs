@@ -4,9 +4,31 @@ use thiserror::Error;
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
 pub struct Address(pub u16);
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+impl Address {
+    /// Gets the address `offset` bytes past this one, wrapping around the 16 bit address space
+    /// ```
+    /// # use eoxchip8::core::memory::Address;
+    /// assert_eq!(Address(0x200).offset(2), Address(0x202));
+    /// assert_eq!(Address(0xFFFF).offset(1), Address(0));
+    /// ```
+    #[must_use]
+    pub fn offset(self, offset: u16) -> Address {
+        Address(self.0.wrapping_add(offset))
+    }
+}
+
+/// Where programs are loaded, and execution starts
+pub const PROGRAM_START: Address = Address(0x200);
+
+/// Size of the original Chip8's memory
+pub const CHIP8_MEMORY_SIZE: usize = 0x1000;
+
+/// Size of XO-CHIP's memory, the full 16 bit address space
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
 pub struct Ram {
-    data: [u8; 4096],
+    data: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Error)]
@@ -15,6 +37,8 @@ pub enum MemoryAccessError {
     AddressOutOfBounds(Address),
     #[error("Address unaligned for access: {0:?}")]
     AddressUnaligned(Address),
+    #[error("Font doesn't fit below the program: {0:?}")]
+    FontOverlapsProgram(Address),
 }
 
 type MemoryResult<T> = Result<T, MemoryAccessError>;
@@ -22,7 +46,7 @@ type MemoryResult<T> = Result<T, MemoryAccessError>;
 impl Ram {
     /// Create a new stick of Chip-8 RAM
     /// ```
-    /// # use rc8::core::memory::*;
+    /// # use eoxchip8::core::memory::*;
     /// let ram = Ram::new();
     /// # for addr in 0x200..4096 {
     /// # assert_eq!(ram.get(Address(addr)), Ok(0));
@@ -33,6 +57,29 @@ impl Ram {
         Ram::default()
     }
 
+    /// Create RAM holding `size` bytes, at most the 64 KiB a 16 bit address can reach
+    /// ```
+    /// # use eoxchip8::core::memory::*;
+    /// let ram = Ram::with_size(XO_CHIP_MEMORY_SIZE);
+    /// assert_eq!(ram.get(Address(0xFFFF)), Ok(0));
+    /// ```
+    #[must_use]
+    pub fn with_size(size: usize) -> Self {
+        Ram {
+            data: vec![0; size.min(XO_CHIP_MEMORY_SIZE)],
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn get(&self, address: Address) -> MemoryResult<u8> {
         if address.0 as usize >= self.data.len() {
             return Err(MemoryAccessError::AddressOutOfBounds(address));
@@ -56,8 +103,17 @@ impl Ram {
             return Err(MemoryAccessError::AddressUnaligned(address));
         }
 
+        self.get_wide_unaligned(address)
+    }
+
+    /// Reads two bytes, starting at any address
+    pub fn get_wide_unaligned(&self, address: Address) -> MemoryResult<u16> {
+        let next_address = address
+            .0
+            .checked_add(1)
+            .ok_or(MemoryAccessError::AddressOutOfBounds(address))?;
         let mut data = u16::from(self.get(address)?) << 8;
-        data |= u16::from(self.get(Address(address.0 + 1))?);
+        data |= u16::from(self.get(Address(next_address))?);
 
         Ok(data)
     }
@@ -66,7 +122,7 @@ impl Ram {
     pub fn load_program(&mut self, program: &[u8]) -> MemoryResult<()> {
         if 0x200 + program.len() > self.data.len() {
             return Err(MemoryAccessError::AddressOutOfBounds(Address(
-                0x200 + program.len() as u16,
+                (0x200 + program.len()).min(u16::MAX as usize) as u16,
             )));
         }
         for (offset, byte) in program.iter().enumerate() {
@@ -74,17 +130,45 @@ impl Ram {
         }
         Ok(())
     }
+
+    /// Copies font glyphs into memory, starting at `base`
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn load_font(&mut self, base: Address, font: &[u8]) -> MemoryResult<()> {
+        let start = base.0 as usize;
+        if start + font.len() > self.data.len() {
+            return Err(MemoryAccessError::AddressOutOfBounds(Address(
+                base.0.saturating_add(font.len() as u16),
+            )));
+        }
+        self.data[start..start + font.len()].copy_from_slice(font);
+        Ok(())
+    }
 }
 
 impl Default for Ram {
     fn default() -> Self {
-        Ram { data: [0; 4096] }
+        Ram::with_size(CHIP8_MEMORY_SIZE)
     }
 }
 
+const DISPLAY_WIDTH: usize = 128;
+const DISPLAY_HEIGHT: usize = 64;
+
+/// Number of bitplanes, only XO-CHIP programs draw to more than the first
+pub const DISPLAY_PLANES: usize = 2;
+
+type Plane = [[bool; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+
+/// The Chip8's display
+///
+/// Normally 64x32, a SUPER-CHIP program can switch it to a 128x64 high resolution mode.
+/// XO-CHIP adds a second bitplane, giving four colors. Clearing and scrolling only
+/// affect the selected planes, which is just the first one unless a program selects others.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
 pub struct Chip8Display {
-    data: [[bool; 64]; 32],
+    planes: [Plane; DISPLAY_PLANES],
+    selected_planes: u8,
+    hires: bool,
     has_changed: bool,
 }
 
@@ -94,23 +178,145 @@ impl Chip8Display {
         Chip8Display::default()
     }
 
-    /// Clears the Chip8's display
+    /// Clears the selected planes of the Chip8's display
     pub fn clear(&mut self) {
-        self.data = [[false; 64]; 32];
+        for plane in self.selected_plane_indices() {
+            self.planes[plane] = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+        }
         self.has_changed = true;
     }
 
-    /// Flips a pixel in the Chip8's display
-    pub fn flip_pixel(&mut self, x: u8, y: u8) {
-        debug!("Flipping pixel at ({x}, {y})");
-        self.data[y as usize][x as usize] ^= true;
+    /// Flips a pixel in the first plane of the Chip8's display, returning whether a lit pixel
+    /// was turned off
+    pub fn flip_pixel(&mut self, x: u8, y: u8) -> bool {
+        self.flip_plane_pixel(0, x, y)
+    }
+
+    /// Flips a pixel in one plane of the Chip8's display, returning whether a lit pixel was
+    /// turned off
+    pub fn flip_plane_pixel(&mut self, plane: usize, x: u8, y: u8) -> bool {
+        debug!("Flipping pixel at ({x}, {y}) in plane {plane}");
+        let (x_len, y_len) = (self.x_len() as usize, self.y_len() as usize);
+        let pixel = &mut self.planes[plane][..y_len][y as usize][..x_len][x as usize];
+        let was_lit = *pixel;
+        *pixel ^= true;
         self.has_changed = true;
+        was_lit
     }
 
-    /// Gets a reference to the Chip8's display memory
+    /// Gets a reference to the first plane of the Chip8's display memory
+    ///
+    /// Only the first `x_len` pixels of each row are visible in the current resolution.
     #[must_use]
-    pub fn get(&self) -> &[[bool; 64]] {
-        &self.data
+    pub fn get(&self) -> &[[bool; DISPLAY_WIDTH]] {
+        self.get_plane(0)
+    }
+
+    /// Gets a reference to one plane of the Chip8's display memory
+    #[must_use]
+    pub fn get_plane(&self, plane: usize) -> &[[bool; DISPLAY_WIDTH]] {
+        &self.planes[plane][..self.y_len() as usize]
+    }
+
+    /// Gets the color of a pixel, with bit N set when the pixel is lit in plane N
+    #[must_use]
+    pub fn pixel(&self, x: u8, y: u8) -> u8 {
+        self.planes
+            .iter()
+            .enumerate()
+            .filter(|(_, plane)| plane[y as usize][x as usize])
+            .fold(0, |color, (index, _)| color | (1 << index))
+    }
+
+    /// Gets the planes affected by clearing, scrolling and drawing, as a bitmask
+    #[must_use]
+    pub fn selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    /// Selects the planes affected by clearing, scrolling and drawing, as a bitmask
+    pub fn select_planes(&mut self, planes: u8) {
+        self.selected_planes = planes & ((1 << DISPLAY_PLANES) - 1);
+    }
+
+    /// Iterates over the indices of the selected planes
+    pub fn selected_plane_indices(&self) -> impl Iterator<Item = usize> {
+        let selected_planes = self.selected_planes;
+        (0..DISPLAY_PLANES).filter(move |plane| selected_planes & (1 << plane) != 0)
+    }
+
+    #[must_use]
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switches between 64x32 and 128x64 resolution, clearing every plane
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.planes = [[[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT]; DISPLAY_PLANES];
+        self.has_changed = true;
+    }
+
+    /// Scrolls the selected planes down by `rows`, leaving blank rows at the top
+    pub fn scroll_down(&mut self, rows: u8) {
+        let y_len = self.y_len() as usize;
+        let rows = (rows as usize).min(y_len);
+        for plane in self.selected_plane_indices() {
+            let data = &mut self.planes[plane];
+            for y in (0..y_len).rev() {
+                data[y] = if y >= rows {
+                    data[y - rows]
+                } else {
+                    [false; DISPLAY_WIDTH]
+                };
+            }
+        }
+        self.has_changed = true;
+    }
+
+    /// Scrolls the selected planes up by `rows`, leaving blank rows at the bottom
+    pub fn scroll_up(&mut self, rows: u8) {
+        let y_len = self.y_len() as usize;
+        let rows = (rows as usize).min(y_len);
+        for plane in self.selected_plane_indices() {
+            let data = &mut self.planes[plane];
+            for y in 0..y_len {
+                data[y] = if y + rows < y_len {
+                    data[y + rows]
+                } else {
+                    [false; DISPLAY_WIDTH]
+                };
+            }
+        }
+        self.has_changed = true;
+    }
+
+    /// Scrolls the selected planes right by `columns`, leaving blank columns at the left
+    pub fn scroll_right(&mut self, columns: u8) {
+        let (x_len, y_len) = (self.x_len() as usize, self.y_len() as usize);
+        let columns = (columns as usize).min(x_len);
+        for plane in self.selected_plane_indices() {
+            for row in &mut self.planes[plane][..y_len] {
+                for x in (0..x_len).rev() {
+                    row[x] = x >= columns && row[x - columns];
+                }
+            }
+        }
+        self.has_changed = true;
+    }
+
+    /// Scrolls the selected planes left by `columns`, leaving blank columns at the right
+    pub fn scroll_left(&mut self, columns: u8) {
+        let (x_len, y_len) = (self.x_len() as usize, self.y_len() as usize);
+        let columns = (columns as usize).min(x_len);
+        for plane in self.selected_plane_indices() {
+            for row in &mut self.planes[plane][..y_len] {
+                for x in 0..x_len {
+                    row[x] = x + columns < x_len && row[x + columns];
+                }
+            }
+        }
+        self.has_changed = true;
     }
 
     #[must_use]
@@ -125,20 +331,30 @@ impl Chip8Display {
     #[must_use]
     #[allow(clippy::cast_possible_truncation)]
     pub fn x_len(&self) -> u8 {
-        self.data[0].len() as u8
+        if self.hires {
+            DISPLAY_WIDTH as u8
+        } else {
+            (DISPLAY_WIDTH / 2) as u8
+        }
     }
 
     #[must_use]
     #[allow(clippy::cast_possible_truncation)]
     pub fn y_len(&self) -> u8 {
-        self.data.len() as u8
+        if self.hires {
+            DISPLAY_HEIGHT as u8
+        } else {
+            (DISPLAY_HEIGHT / 2) as u8
+        }
     }
 }
 
 impl Default for Chip8Display {
     fn default() -> Self {
         Chip8Display {
-            data: [[false; 64]; 32],
+            planes: [[[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT]; DISPLAY_PLANES],
+            selected_planes: 1,
+            hires: false,
             has_changed: false,
         }
     }
@@ -146,12 +362,13 @@ impl Default for Chip8Display {
 
 impl std::fmt::Display for Chip8Display {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for column in &self.data {
-            for pixel in column {
-                if *pixel {
-                    write!(f, "█")?;
-                } else {
-                    write!(f, " ")?;
+        for y in 0..self.y_len() {
+            for x in 0..self.x_len() {
+                match self.pixel(x, y) {
+                    0 => write!(f, " ")?,
+                    1 => write!(f, "█")?,
+                    2 => write!(f, "░")?,
+                    _ => write!(f, "▓")?,
                 }
             }
             writeln!(f)?;
@@ -160,24 +377,6 @@ impl std::fmt::Display for Chip8Display {
     }
 }
 
-#[must_use]
-pub fn memory_to_flip_instructions(memory: &[u8]) -> Vec<Vec<bool>> {
-    memory
-        .iter()
-        .map(|byte| byte_to_flip_instructions(*byte))
-        .collect()
-}
-
-fn byte_to_flip_instructions(byte: u8) -> Vec<bool> {
-    let mut mask = 0x80;
-    let mut result = vec![];
-    for _ in 0..u8::BITS {
-        result.push((byte & mask) != 0);
-        mask >>= 1;
-    }
-    result
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -221,19 +420,36 @@ mod tests {
     #[test]
     fn test_load_long_program() {
         let mut ram = Ram::new();
-        let program = [0x20; (u16::MAX - 0x200) as usize];
+        let program = vec![0x20; (u16::MAX - 0x200) as usize];
         assert_eq!(
             ram.load_program(&program),
             Err(MemoryAccessError::AddressOutOfBounds(Address(u16::MAX)))
         );
     }
 
+    #[test]
+    fn test_load_font() {
+        let mut ram = Ram::new();
+        ram.load_font(Address(0x50), &[0xF0, 0x90]).unwrap();
+        assert_eq!(ram.get(Address(0x50)), Ok(0xF0));
+        assert_eq!(ram.get(Address(0x51)), Ok(0x90));
+    }
+
+    #[test]
+    fn test_load_font_out_of_bounds() {
+        let mut ram = Ram::new();
+        assert_eq!(
+            ram.load_font(Address(4095), &[0xF0, 0x90]),
+            Err(MemoryAccessError::AddressOutOfBounds(Address(4097)))
+        );
+    }
+
     #[test]
     fn test_flip_pixels_display() {
         let mut display = Chip8Display::new();
-        display.flip_pixel(0, 0);
+        assert!(!display.flip_pixel(0, 0));
         assert!(display.get()[0][0]);
-        display.flip_pixel(0, 0);
+        assert!(display.flip_pixel(0, 0));
         assert!(!display.get()[0][0]);
     }
 
@@ -250,14 +466,60 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    fn test_hires_display() {
+        let mut display = Chip8Display::new();
+        display.set_hires(true);
+        assert_eq!((display.x_len(), display.y_len()), (128, 64));
+        assert!(!display.flip_pixel(127, 63));
+        assert!(display.get()[63][127]);
+        display.set_hires(false);
+        assert_eq!((display.x_len(), display.y_len()), (64, 32));
+        assert!(!display.get()[0][0]);
+    }
+
+    #[test]
+    fn test_scroll_display() {
+        let mut display = Chip8Display::new();
+        display.flip_pixel(10, 10);
+        display.scroll_down(3);
+        assert!(display.get()[13][10]);
+        display.scroll_right(4);
+        assert!(display.get()[13][14]);
+        display.scroll_left(4);
+        display.scroll_left(4);
+        assert!(display.get()[13][6]);
+        assert!(!display.get()[13][10]);
+        display.scroll_down(32);
+        assert!(!display.get()[13][6]);
+    }
+
+    #[test]
+    fn test_display_planes() {
+        let mut display = Chip8Display::new();
+        display.flip_plane_pixel(1, 2, 2);
+        display.flip_pixel(3, 3);
+        display.flip_plane_pixel(1, 3, 3);
+        assert_eq!(display.pixel(2, 2), 2);
+        assert_eq!(display.pixel(3, 3), 3);
+        display.clear();
+        assert_eq!(display.pixel(2, 2), 2);
+        assert_eq!(display.pixel(3, 3), 2);
+        display.select_planes(3);
+        display.scroll_up(1);
+        assert_eq!(display.pixel(2, 1), 2);
+        display.clear();
+        assert_eq!(display.pixel(2, 1), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
     fn test_display_out_of_bounds_access_x() {
         let mut display = Chip8Display::new();
         display.flip_pixel(64, 0);
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "index out of bounds")]
     fn test_display_out_of_bounds_access_y() {
         let mut display = Chip8Display::new();
         display.flip_pixel(0, 32);
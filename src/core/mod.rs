@@ -1,2 +1,11 @@
+pub mod audio;
+pub mod clock;
 pub mod cpu;
+pub mod font;
+pub mod keypad;
 pub mod memory;
+pub mod platform;
+pub mod rng;
+pub mod rpl;
+pub mod scheduler;
+pub mod wav;
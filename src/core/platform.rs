@@ -0,0 +1,74 @@
+use crate::core::{
+    cpu::{instructions::Instruction, quirks::Quirks},
+    memory::{CHIP8_MEMORY_SIZE, XO_CHIP_MEMORY_SIZE},
+};
+
+/// The Chip8 dialect a program is written for
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub enum Platform {
+    /// The original Chip8 instruction set, on a 64x32 display
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1, adding a 128x64 high resolution mode, scrolling and large sprites
+    SuperChip,
+    /// XO-CHIP, extending SUPER-CHIP with 64 KiB of memory, two bitplanes and audio
+    XoChip,
+}
+
+impl Platform {
+    /// Gets the quirks programs for this platform usually expect
+    #[must_use]
+    pub fn default_quirks(&self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::default(),
+            Platform::SuperChip => Quirks::super_chip_1_1(),
+            Platform::XoChip => Quirks::xo_chip(),
+        }
+    }
+
+    /// Whether the SUPER-CHIP instructions are available
+    #[must_use]
+    pub const fn is_super_chip(&self) -> bool {
+        matches!(self, Platform::SuperChip | Platform::XoChip)
+    }
+
+    /// Whether the XO-CHIP instructions are available
+    #[must_use]
+    pub const fn is_xo_chip(&self) -> bool {
+        matches!(self, Platform::XoChip)
+    }
+
+    /// Gets the number of bytes of memory programs can address
+    #[must_use]
+    pub const fn memory_size(&self) -> usize {
+        if self.is_xo_chip() {
+            XO_CHIP_MEMORY_SIZE
+        } else {
+            CHIP8_MEMORY_SIZE
+        }
+    }
+
+    /// Whether programs on this platform may execute `instruction`
+    #[must_use]
+    pub const fn supports(&self, instruction: &Instruction) -> bool {
+        match instruction {
+            Instruction::ScrollDown { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::LowRes
+            | Instruction::HighRes
+            | Instruction::LoadIBigSprite { .. }
+            | Instruction::SaveRegistersToFlags { .. }
+            | Instruction::LoadRegistersFromFlags { .. } => self.is_super_chip(),
+            Instruction::ScrollUp { .. }
+            | Instruction::SaveRegisterRange { .. }
+            | Instruction::LoadRegisterRange { .. }
+            | Instruction::SelectPlanes { .. }
+            | Instruction::LoadILong { .. }
+            | Instruction::LoadAudioPattern
+            | Instruction::SetPitch { .. } => self.is_xo_chip(),
+            _ => true,
+        }
+    }
+}
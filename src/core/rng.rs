@@ -0,0 +1,67 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A source of random bytes for the CXNN instruction
+pub trait RandomSource {
+    fn next_byte(&mut self) -> u8;
+}
+
+/// A small, seedable xorshift64* generator
+///
+/// The same seed always produces the same sequence of bytes, which keeps
+/// recorded runs reproducible.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+    /// Creates a generator with a fixed seed
+    /// ```
+    /// # use eoxchip8::core::rng::{RandomSource, XorShiftRng};
+    /// let mut first = XorShiftRng::from_seed(42);
+    /// let mut second = XorShiftRng::from_seed(42);
+    /// assert_eq!(first.next_byte(), second.next_byte());
+    /// ```
+    #[must_use]
+    pub fn from_seed(seed: u64) -> Self {
+        // xorshift gets stuck on an all zero state
+        let state = if seed == 0 { Self::DEFAULT_SEED } else { seed };
+        XorShiftRng { state }
+    }
+
+    /// Creates a generator seeded from the system, for normal play
+    #[must_use]
+    pub fn from_entropy() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            hasher.write_u128(now.as_nanos());
+        }
+        XorShiftRng::from_seed(hasher.finish())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+impl Default for XorShiftRng {
+    fn default() -> Self {
+        XorShiftRng::from_seed(Self::DEFAULT_SEED)
+    }
+}
+
+impl RandomSource for XorShiftRng {
+    #[allow(clippy::cast_possible_truncation)]
+    fn next_byte(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
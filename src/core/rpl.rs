@@ -0,0 +1,134 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+/// Number of RPL user flags, XO-CHIP extends SUPER-CHIP's 8 to cover every register
+pub const RPL_FLAG_COUNT: usize = 16;
+
+pub type RplFlags = [u8; RPL_FLAG_COUNT];
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Error)]
+pub enum RplStorageError {
+    #[error("Error accessing RPL flag storage: {0}")]
+    Io(ErrorKind),
+}
+
+/// Somewhere to keep the SUPER-CHIP RPL user flags, which outlive a single run
+pub trait RplStorage {
+    /// Reads the stored flags, all zero if nothing has been stored yet
+    fn load(&mut self) -> Result<RplFlags, RplStorageError>;
+
+    fn save(&mut self, flags: &RplFlags) -> Result<(), RplStorageError>;
+}
+
+/// Keeps the flags in memory, they are lost along with the executor
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub struct MemoryRplStorage {
+    flags: RplFlags,
+}
+
+impl MemoryRplStorage {
+    #[must_use]
+    pub fn new() -> Self {
+        MemoryRplStorage::default()
+    }
+}
+
+impl RplStorage for MemoryRplStorage {
+    fn load(&mut self) -> Result<RplFlags, RplStorageError> {
+        Ok(self.flags)
+    }
+
+    fn save(&mut self, flags: &RplFlags) -> Result<(), RplStorageError> {
+        self.flags = *flags;
+        Ok(())
+    }
+}
+
+/// Keeps the flags in a small file, so they persist between runs
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq)]
+pub struct FileRplStorage {
+    path: PathBuf,
+}
+
+impl FileRplStorage {
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        FileRplStorage { path }
+    }
+
+    /// Stores the flags for a ROM next to it, with `.rpl` appended to its name
+    ///
+    /// The ROM's own extension is kept, so the flags can never be written over the ROM.
+    /// ```
+    /// # use std::path::Path;
+    /// # use eoxchip8::core::rpl::FileRplStorage;
+    /// let storage = FileRplStorage::for_rom(Path::new("roms/car.ch8"));
+    /// assert_eq!(storage.path(), Path::new("roms/car.ch8.rpl"));
+    /// let storage = FileRplStorage::for_rom(Path::new("roms/car.rpl"));
+    /// assert_eq!(storage.path(), Path::new("roms/car.rpl.rpl"));
+    /// ```
+    #[must_use]
+    pub fn for_rom(rom_path: &Path) -> Self {
+        let mut path = rom_path.as_os_str().to_owned();
+        path.push(".rpl");
+        FileRplStorage::new(path.into())
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl RplStorage for FileRplStorage {
+    fn load(&mut self) -> Result<RplFlags, RplStorageError> {
+        let mut flags = [0; RPL_FLAG_COUNT];
+        match fs::read(&self.path) {
+            Ok(data) => {
+                let len = data.len().min(RPL_FLAG_COUNT);
+                flags[..len].copy_from_slice(&data[..len]);
+                Ok(flags)
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(flags),
+            Err(error) => Err(RplStorageError::Io(error.kind())),
+        }
+    }
+
+    fn save(&mut self, flags: &RplFlags) -> Result<(), RplStorageError> {
+        fs::write(&self.path, flags).map_err(|error| RplStorageError::Io(error.kind()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_storage_round_trip() {
+        let mut storage = MemoryRplStorage::new();
+        assert_eq!(storage.load(), Ok([0; RPL_FLAG_COUNT]));
+        let mut flags = [0; RPL_FLAG_COUNT];
+        flags[3] = 42;
+        storage.save(&flags).unwrap();
+        assert_eq!(storage.load(), Ok(flags));
+    }
+
+    #[test]
+    fn test_file_storage_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("eoxchip8-rpl-test-{}.rpl", std::process::id()));
+        let mut storage = FileRplStorage::new(path.clone());
+        assert_eq!(storage.load(), Ok([0; RPL_FLAG_COUNT]));
+        let mut flags = [0; RPL_FLAG_COUNT];
+        flags[0] = 7;
+        flags[15] = 0xFF;
+        storage.save(&flags).unwrap();
+        assert_eq!(FileRplStorage::new(path.clone()).load(), Ok(flags));
+        fs::remove_file(path).unwrap();
+    }
+}
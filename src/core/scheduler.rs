@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use crate::core::clock::{Clock, WallClock};
+
+/// Frames the pacer will run back to back to catch up, before giving up and skipping ahead
+pub const DEFAULT_MAX_CATCH_UP_FRAMES: u32 = 4;
+
+/// Splits a per-second instruction rate into whole instructions per frame
+///
+/// When the rate doesn't divide evenly, the remainder is carried from frame to frame, so
+/// 700 instructions per second at 60 frames per second alternates between 11 and 12
+/// instructions while still running exactly 700 each second.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub struct CycleBudget {
+    cycles_per_second: u32,
+    frame_rate: u32,
+    remainder: u32,
+}
+
+impl CycleBudget {
+    /// # Panics
+    ///
+    /// Panics if `frame_rate` is 0
+    #[must_use]
+    pub fn new(cycles_per_second: u32, frame_rate: u32) -> Self {
+        assert!(frame_rate > 0, "frame rate must be positive");
+        CycleBudget {
+            cycles_per_second,
+            frame_rate,
+            remainder: 0,
+        }
+    }
+
+    /// The instructions to run in the next frame
+    pub fn next_frame(&mut self) -> u32 {
+        let total = u64::from(self.cycles_per_second) + u64::from(self.remainder);
+        let frame_rate = u64::from(self.frame_rate);
+        // Both fit a u32, as they're at most the rate plus a remainder below the frame rate
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.remainder = (total % frame_rate) as u32;
+            (total / frame_rate) as u32
+        }
+    }
+}
+
+/// Paces frames against a [`Clock`]
+///
+/// Frames are scheduled on a fixed timeline from the start time rather than relative to when
+/// the last one finished, so time spent running and rendering doesn't accumulate as drift.
+/// If the frontend falls behind, the missed frames are reported as due so they can be run
+/// back to back, up to a limit past which the timeline is moved up to the present instead.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub struct FramePacer<C = WallClock> {
+    clock: C,
+    frame_period: Duration,
+    next_frame: Duration,
+    max_catch_up_frames: u32,
+}
+
+impl<C: Clock> FramePacer<C> {
+    /// Creates a pacer running `frame_rate` frames per second, with the first frame due now
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame_rate` is 0
+    #[must_use]
+    pub fn new(frame_rate: u32, clock: C) -> Self {
+        assert!(frame_rate > 0, "frame rate must be positive");
+        FramePacer {
+            next_frame: clock.now(),
+            clock,
+            frame_period: Duration::from_secs(1) / frame_rate,
+            max_catch_up_frames: DEFAULT_MAX_CATCH_UP_FRAMES,
+        }
+    }
+
+    #[must_use]
+    pub fn frame_period(&self) -> Duration {
+        self.frame_period
+    }
+
+    #[must_use]
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    /// Sets how many frames may be run at once when behind, at least 1
+    pub fn set_max_catch_up_frames(&mut self, max_catch_up_frames: u32) {
+        self.max_catch_up_frames = max_catch_up_frames.max(1);
+    }
+
+    /// Time left until the next frame is due, zero if it's already due
+    #[must_use]
+    pub fn time_until_next_frame(&self) -> Duration {
+        self.next_frame.saturating_sub(self.clock.now())
+    }
+
+    /// The number of frames due now, advancing the timeline past them
+    pub fn frames_due(&mut self) -> u32 {
+        let now = self.clock.now();
+        let Some(late_by) = now.checked_sub(self.next_frame) else {
+            return 0;
+        };
+        let behind = late_by.as_nanos() / self.frame_period.as_nanos();
+        let due = behind.saturating_add(1);
+        if due > u128::from(self.max_catch_up_frames) {
+            self.next_frame = now + self.frame_period;
+            return self.max_catch_up_frames;
+        }
+        // Checked against the catch-up limit above, so it fits a u32
+        #[allow(clippy::cast_possible_truncation)]
+        let due = due as u32;
+        self.next_frame += self.frame_period * due;
+        due
+    }
+
+    /// Waits on the clock for the next frame, then returns the number of frames due
+    pub fn wait_for_frames(&mut self) -> u32 {
+        self.clock.sleep_until(self.next_frame);
+        self.frames_due()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::clock::VirtualClock;
+
+    #[test]
+    fn test_cycle_budget_carries_remainder() {
+        let mut budget = CycleBudget::new(700, 60);
+        let cycles: Vec<u32> = (0..60).map(|_| budget.next_frame()).collect();
+        assert_eq!(cycles.iter().sum::<u32>(), 700);
+        assert!(cycles.iter().all(|&cycles| cycles == 11 || cycles == 12));
+    }
+
+    #[test]
+    fn test_pacer_follows_fixed_timeline() {
+        let mut pacer = FramePacer::new(60, VirtualClock::new());
+        let period = pacer.frame_period();
+        assert_eq!(pacer.frames_due(), 1);
+        pacer.clock_mut().advance(period / 2);
+        assert_eq!(pacer.frames_due(), 0);
+        assert_eq!(pacer.time_until_next_frame(), period / 2);
+        // Running late doesn't push back the frames after
+        pacer.clock_mut().advance(period);
+        assert_eq!(pacer.frames_due(), 1);
+        assert_eq!(pacer.time_until_next_frame(), period / 2);
+    }
+
+    #[test]
+    fn test_pacer_catches_up_then_skips() {
+        let mut pacer = FramePacer::new(60, VirtualClock::new());
+        let period = pacer.frame_period();
+        pacer.clock_mut().advance(period * 2);
+        assert_eq!(pacer.frames_due(), 3);
+        pacer.clock_mut().advance(period * 98);
+        assert_eq!(pacer.frames_due(), 4);
+        assert_eq!(pacer.frames_due(), 0);
+        assert_eq!(pacer.time_until_next_frame(), period);
+    }
+
+    #[test]
+    fn test_pacer_waits_on_clock() {
+        let mut pacer = FramePacer::new(60, VirtualClock::new());
+        let period = pacer.frame_period();
+        for frame in 0..10 {
+            assert_eq!(pacer.wait_for_frames(), 1);
+            assert_eq!(pacer.clock().now(), period * frame);
+        }
+    }
+}
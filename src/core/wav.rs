@@ -0,0 +1,133 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+const HEADER_SIZE: u32 = 44;
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+const BYTES_PER_SAMPLE: u32 = (BITS_PER_SAMPLE / 8) as u32;
+/// The most samples whose size still fits the 32 bit RIFF chunk size
+const MAX_SAMPLES: u32 = (u32::MAX - (HEADER_SIZE - 8)) / BYTES_PER_SAMPLE;
+
+/// Writes mono, signed 16 bit PCM to a WAV file
+///
+/// The header is kept up to date after every write, so the file stays playable even if
+/// the emulator is stopped without calling `finish`.
+#[derive(Debug)]
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    samples_written: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        writer.write_all(&header(sample_rate, 0))?;
+        Ok(WavWriter {
+            writer,
+            sample_rate,
+            samples_written: 0,
+        })
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        let samples_written = u32::try_from(samples.len())
+            .ok()
+            .and_then(|len| self.samples_written.checked_add(len))
+            .filter(|&total| total <= MAX_SAMPLES)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "WAV file too long"))?;
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        self.writer.write_all(&bytes)?;
+        self.samples_written = samples_written;
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer
+            .write_all(&header(self.sample_rate, self.samples_written))?;
+        self.writer.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn samples_written(&self) -> u32 {
+        self.samples_written
+    }
+
+    /// Flushes the file, handing back the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Builds the 44 byte header; `samples` must not exceed `MAX_SAMPLES`
+fn header(sample_rate: u32, samples: u32) -> Vec<u8> {
+    debug_assert!(samples <= MAX_SAMPLES);
+    let data_size = samples * BYTES_PER_SAMPLE;
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let byte_rate = sample_rate * u32::from(block_align);
+
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // Uncompressed PCM
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&CHANNELS.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_wav() {
+        let writer = WavWriter::new(Cursor::new(vec![]), 8000).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(data.len(), HEADER_SIZE as usize);
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(&data[4..8], &36u32.to_le_bytes());
+        assert_eq!(&data[24..28], &8000u32.to_le_bytes());
+        assert_eq!(&data[40..44], &0u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_header_tracks_samples() {
+        let mut writer = WavWriter::new(Cursor::new(vec![]), 44100).unwrap();
+        writer.write_samples(&[1, -1]).unwrap();
+        writer.write_samples(&[i16::MAX]).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(data.len(), HEADER_SIZE as usize + 6);
+        assert_eq!(&data[4..8], &42u32.to_le_bytes());
+        assert_eq!(&data[40..44], &6u32.to_le_bytes());
+        assert_eq!(&data[44..], &[0x01, 0x00, 0xFF, 0xFF, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn test_rejects_oversized_file() {
+        let mut writer = WavWriter::new(Cursor::new(vec![]), 44100).unwrap();
+        writer.samples_written = MAX_SAMPLES - 1;
+        writer.write_samples(&[0]).unwrap();
+        assert_eq!(writer.samples_written(), MAX_SAMPLES);
+        let error = writer.write_samples(&[0]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(writer.samples_written(), MAX_SAMPLES);
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(data.len(), HEADER_SIZE as usize + 2);
+        assert_eq!(&data[4..8], &(u32::MAX - 1).to_le_bytes());
+    }
+}
@@ -4,4 +4,5 @@
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::missing_errors_doc)]
 
+pub mod assembler;
 pub mod core;